@@ -0,0 +1,129 @@
+use crate::util;
+use log::info;
+use std::{net::IpAddr, time::Duration};
+
+/// The format of the access log enabled with `Bobby::with_access_log`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// A short line with the method, target, status and duration, like
+    /// `GET /users/5 200 0.21ms`, for reading in a terminal.
+    Simple,
+    /// Apache's Combined Log Format.
+    Combined,
+    /// One JSON object per request, with the fields `method`, `path`,
+    /// `matched_route`, `status`, `duration_ms`, `client_ip` and `bytes`.
+    Json,
+}
+
+// What gets logged about a single request once its response is ready.
+pub(crate) struct AccessLogEntry {
+    pub(crate) method: String,
+    pub(crate) target: String,
+    pub(crate) path: String,
+    pub(crate) version: String,
+    pub(crate) matched_route: Option<String>,
+    pub(crate) status: u16,
+    pub(crate) duration: Duration,
+    pub(crate) client_ip: Option<IpAddr>,
+    pub(crate) bytes: Option<u64>,
+    pub(crate) referer: Option<String>,
+    pub(crate) user_agent: Option<String>,
+}
+
+impl AccessLogEntry {
+    pub(crate) fn write(&self, format: AccessLogFormat) {
+        let line = match format {
+            AccessLogFormat::Simple => self.simple(),
+            AccessLogFormat::Combined => self.combined(),
+            AccessLogFormat::Json => self.json(),
+        };
+
+        info!(target: "bobby::access", "{}", line);
+    }
+
+    fn simple(&self) -> String {
+        format!(
+            "{} {} {} {:.2}ms",
+            self.method,
+            self.target,
+            self.status,
+            self.duration.as_secs_f64() * 1000.0
+        )
+    }
+
+    fn combined(&self) -> String {
+        let quoted = |value: &Option<String>| match value {
+            Some(value) => format!("\"{}\"", value.replace('"', "\\\"")),
+            None => String::from("\"-\""),
+        };
+
+        format!(
+            "{ip} - - [{time}] \"{method} {target} {version}\" {status} {bytes} {referer} {user_agent}",
+            ip = self
+                .client_ip
+                .map_or_else(|| String::from("-"), |ip| ip.to_string()),
+            time = clf_time(),
+            method = self.method,
+            target = self.target,
+            version = self.version,
+            status = self.status,
+            bytes = self
+                .bytes
+                .map_or_else(|| String::from("-"), |bytes| bytes.to_string()),
+            referer = quoted(&self.referer),
+            user_agent = quoted(&self.user_agent),
+        )
+    }
+
+    // Written by hand rather than with serde_json, so the format works
+    // without the `json` feature.
+    fn json(&self) -> String {
+        let string = |value: &str| format!("\"{}\"", escape_json(value));
+        let or_null = |value: Option<String>| value.unwrap_or_else(|| String::from("null"));
+
+        format!(
+            "{{\"method\":{},\"path\":{},\"matched_route\":{},\"status\":{},\"duration_ms\":{},\"client_ip\":{},\"bytes\":{}}}",
+            string(&self.method),
+            string(&self.path),
+            or_null(self.matched_route.as_deref().map(string)),
+            self.status,
+            self.duration.as_secs_f64() * 1000.0,
+            or_null(self.client_ip.map(|ip| string(&ip.to_string()))),
+            or_null(self.bytes.map(|bytes| bytes.to_string())),
+        )
+    }
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// The current time as `14/Oct/2026:11:18:13 +0000`, rearranged from the HTTP
+// date format (`Wed, 14 Oct 2026 11:18:13 GMT`).
+fn clf_time() -> String {
+    let Some(now) = util::system_now() else {
+        return String::from("-");
+    };
+
+    let date = httpdate::fmt_http_date(now);
+    let parts: Vec<&str> = date.split(' ').collect();
+
+    match parts.as_slice() {
+        [_, day, month, year, time, _] => format!("{}/{}/{}:{} +0000", day, month, year, time),
+        _ => date,
+    }
+}
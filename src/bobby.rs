@@ -1,47 +1,118 @@
-use hyper::{header, service::service_fn};
-use hyper_util::{
-    rt::TokioIo,
-    server::conn::auto::{self},
+use crate::{
+    access_log::{AccessLogEntry, AccessLogFormat},
+    body::{Body, BodyStream, BoxError, CancelOnDrop, HttpBody},
+    cache::{CachedResponse, ResponseCache},
+    cookie::{Cookie, parse_cookies},
+    cors::{self, Cors},
+    csv,
+    pattern::{self, RouteConflict, RouteError, TrailingSlash},
+    query::{is_well_formed, parse_query},
+    route_index::RouteIndex,
+    util::{self, collapse_slashes, content_type, percent_encode, with_query},
 };
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt, stream};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{HeaderMap, ext::ReasonPhrase, header, http::Extensions};
 use log::{debug, error, info, trace, warn};
+use regex::Regex;
 use std::{
     collections::HashMap,
-    net::{IpAddr, SocketAddr},
-    sync::Arc,
+    net::{AddrParseError, IpAddr, SocketAddr},
+    path::Path,
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
 };
-use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 
-#[derive(Clone)]
-struct TokioExecutor;
-
-impl<F> hyper::rt::Executor<F> for TokioExecutor
-where
-    F: std::future::Future + Send + 'static,
-    F::Output: Send + 'static,
-{
-    fn execute(&self, fut: F) {
-        tokio::task::spawn(fut);
-    }
+#[cfg(feature = "json")]
+use crate::{
+    json::{JsonError, JsonLimits},
+    openapi::{self, OpenApi},
+};
+#[cfg(feature = "json")]
+use serde::{Serialize, de::DeserializeOwned};
+
+#[cfg(feature = "fs")]
+use crate::static_files::StaticFiles;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+#[cfg(feature = "fs")]
+use tokio_util::io::ReaderStream;
+
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "test-util")]
+mod test_client;
+
+#[cfg(feature = "server")]
+pub use server::{ConnectHook, Tunnel};
+#[cfg(feature = "test-util")]
+pub use test_client::{TestClient, TestResponse};
+
+/// The form of the request target, as in RFC 9112 section 3.2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetForm {
+    /// `/path?query`, what most requests use.
+    Origin,
+    /// `http://example.com/path`, used with forward proxies. HTTP/2 requests
+    /// always show up in this form, as they carry scheme and authority.
+    Absolute,
+    /// `example.com:443`, used by `CONNECT`.
+    Authority,
+    /// `*`, used by server-wide `OPTIONS`.
+    Asterisk,
 }
 
-impl TokioExecutor {
-    pub fn new() -> Self {
-        Self {}
-    }
+/// What a request body most likely holds, as guessed by
+/// `Request::guess_body_kind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyKind {
+    Json,
+    /// `application/x-www-form-urlencoded`.
+    Form,
 }
 
 pub struct Request {
     method: hyper::Method,
     uri: hyper::Uri,
+    target: String,
     params: HashMap<String, String>,
+    queries: HashMap<String, String>,
+    headers: HeaderMap,
+    cookies: HashMap<String, String>,
+    extensions: Extensions,
+    state: Arc<Extensions>,
+    body: Bytes,
+    body_stream: Option<BodyStream>,
+    #[cfg(feature = "json")]
+    json_limits: JsonLimits,
+    trust_proxy: bool,
+    cancellation: CancellationToken,
 }
 
 impl Request {
-    pub fn new(request: &hyper::Request<hyper::body::Incoming>) -> Self {
+    pub fn new<B>(request: &hyper::Request<B>) -> Self {
         Request {
             method: request.method().clone(),
             uri: request.uri().clone(),
+            target: request.uri().to_string(),
             params: HashMap::new(),
+            queries: parse_query(request.uri().query().unwrap_or_default()),
+            headers: request.headers().clone(),
+            cookies: parse_cookies(request.headers()),
+            extensions: request.extensions().clone(),
+            state: Arc::new(Extensions::new()),
+            body: Bytes::new(),
+            body_stream: None,
+            #[cfg(feature = "json")]
+            json_limits: JsonLimits::default(),
+            trust_proxy: false,
+            cancellation: CancellationToken::new(),
         }
     }
 
@@ -53,11 +124,283 @@ impl Request {
         &self.uri
     }
 
+    /// The request target as received, before any `before_route` rewrites.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn target_form(&self) -> TargetForm {
+        if self.target == "*" {
+            TargetForm::Asterisk
+        } else if self.method == hyper::Method::CONNECT && !self.target.starts_with('/') {
+            TargetForm::Authority
+        } else if self.target.starts_with('/') {
+            TargetForm::Origin
+        } else {
+            TargetForm::Absolute
+        }
+    }
+
     pub fn param(&self, name: &str) -> Option<&String> {
         self.params.get(name)
     }
+
+    /// The route param `name` parsed as `T`, or `None` when it's missing or
+    /// doesn't parse.
+    pub fn param_as<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.param_as_result(name).ok()
+    }
+
+    /// Like `param_as`, but telling a missing param apart from one that
+    /// doesn't parse, e.g. to answer the latter with a 400.
+    pub fn param_as_result<T: FromStr>(&self, name: &str) -> Result<T, ValueError<T::Err>> {
+        parse_value(name, self.params.get(name))
+    }
+
+    pub fn params(&self) -> &HashMap<String, String> {
+        &self.params
+    }
+
+    /// A query string parameter, with percent-encoding and `+` decoded.
+    pub fn query(&self, name: &str) -> Option<&String> {
+        self.queries.get(name)
+    }
+
+    /// The query string parameter `name` parsed as `T`, or `None` when it's
+    /// missing or doesn't parse, e.g. `req.query_as("page").unwrap_or(1)`.
+    pub fn query_as<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.query_as_result(name).ok()
+    }
+
+    /// Like `query_as`, but telling a missing parameter apart from one that
+    /// doesn't parse, e.g. to answer the latter with a 400.
+    pub fn query_as_result<T: FromStr>(&self, name: &str) -> Result<T, ValueError<T::Err>> {
+        parse_value(name, self.queries.get(name))
+    }
+
+    /// All query string parameters. When a name repeats, the first value is
+    /// kept.
+    pub fn queries(&self) -> &HashMap<String, String> {
+        &self.queries
+    }
+
+    /// The first value of the header `name`, looked up case-insensitively.
+    /// `None` when it's missing or not valid visible ASCII.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// Every header as a name and value pair, with names lowercased. Headers
+    /// sent more than once appear once per value, and values that aren't
+    /// visible ASCII are skipped.
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+    }
+
+    /// The cookies sent with the request, from all of its `Cookie` headers.
+    pub fn cookies(&self) -> &HashMap<String, String> {
+        &self.cookies
+    }
+
+    /// The value of the cookie called `name`, if the request sent one.
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies.get(name).map(String::as_str)
+    }
+
+    pub fn set_method(&mut self, method: hyper::Method) {
+        self.method = method;
+    }
+
+    pub fn set_uri(&mut self, uri: hyper::Uri) {
+        self.queries = parse_query(uri.query().unwrap_or_default());
+        self.uri = uri;
+    }
+
+    /// The address of the connection the request came in on, or `None` when
+    /// it was handed to `Bobby::respond` rather than received by the server.
+    /// Behind a proxy this is the proxy, see `real_ip` for the client.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.extensions.get::<PeerAddr>().map(|peer| peer.0)
+    }
+
+    /// The client's IP address. When `Bobby::with_trusted_proxy` is enabled
+    /// it's taken from `X-Real-IP`, or else the last `X-Forwarded-For` entry,
+    /// falling back to `remote_addr`.
+    pub fn real_ip(&self) -> Option<IpAddr> {
+        client_ip(&self.headers, self.remote_addr(), self.trust_proxy)
+    }
+
+    /// The scheme and host the client used to reach the app, e.g.
+    /// `https://example.com`. `X-Forwarded-Proto` and `X-Forwarded-Host` are
+    /// only honored when `Bobby::with_trusted_proxy` is enabled.
+    pub fn base_url(&self) -> String {
+        let forwarded = |name: &str| {
+            self.headers
+                .get(name)
+                .filter(|_| self.trust_proxy)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(',').next())
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+
+        let scheme = forwarded("x-forwarded-proto")
+            .or(self.uri.scheme_str())
+            .unwrap_or("http");
+
+        let host = request_host(&self.headers, &self.uri, self.trust_proxy).unwrap_or("localhost");
+
+        format!("{}://{}", scheme, host)
+    }
+
+    /// Joins `path` onto `base_url`, e.g. `https://example.com/login`.
+    pub fn absolute_url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url(), path.trim_start_matches('/'))
+    }
+
+    /// The request body, buffered in full before the handler runs.
+    pub fn body_bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// The request body as UTF-8 text.
+    pub fn body_string(&self) -> Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(&self.body)
+    }
+
+    /// Parses the body as JSON into `T`. Bodies nested deeper, or with more
+    /// values, than configured with `Bobby::with_max_json_depth` and
+    /// `Bobby::with_max_json_values` are rejected before parsing.
+    #[cfg(feature = "json")]
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, JsonError> {
+        self.json_limits.check(&self.body)?;
+
+        serde_json::from_slice(&self.body).map_err(JsonError::Invalid)
+    }
+
+    /// Best-effort guess at what the body holds, for clients that are sloppy
+    /// with their headers. A JSON or urlencoded `Content-Type` is taken at
+    /// its word; anything else falls back to sniffing the body, where a
+    /// leading `{` or `[` means JSON and everything else a form.
+    pub fn guess_body_kind(&self) -> BodyKind {
+        let content_type = self
+            .header("content-type")
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase());
+
+        match content_type.as_deref() {
+            Some("application/json") => return BodyKind::Json,
+            Some(essence) if essence.ends_with("+json") => return BodyKind::Json,
+            Some("application/x-www-form-urlencoded") => return BodyKind::Form,
+            _ => {}
+        }
+
+        match self.body.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{' | b'[') => BodyKind::Json,
+            _ => BodyKind::Form,
+        }
+    }
+
+    /// Consumes the request, yielding its body as a stream of chunks. On
+    /// routes marked with `Route::stream_body` the chunks come straight off
+    /// the connection, with the body size limit enforced as they arrive;
+    /// elsewhere the already buffered body is yielded as a single chunk.
+    pub fn into_body_stream(self) -> BodyStream {
+        match self.body_stream {
+            Some(body_stream) => body_stream,
+            None if self.body.is_empty() => Box::pin(stream::empty()),
+            None => Box::pin(stream::once(async move { Ok(self.body) })),
+        }
+    }
+
+    // Describes the request as received, for the echo endpoint. Repeated
+    // headers are joined with commas, and bodies that aren't UTF-8 are
+    // decoded lossily.
+    #[cfg(feature = "json")]
+    fn echo(&self) -> serde_json::Value {
+        let mut headers = serde_json::Map::new();
+
+        for name in self.headers.keys() {
+            let values: Vec<&str> = self
+                .headers
+                .get_all(name)
+                .iter()
+                .map(|value| value.to_str().unwrap_or_default())
+                .collect();
+
+            headers.insert(String::from(name.as_str()), values.join(", ").into());
+        }
+
+        serde_json::json!({
+            "method": self.method.as_str(),
+            "uri": self.uri.to_string(),
+            "headers": headers,
+            "query": self.queries,
+            "body": String::from_utf8_lossy(&self.body),
+        })
+    }
+
+    /// Cancelled when the server starts shutting down, or when the client
+    /// disconnects before the response was sent in full, so long-running
+    /// work can stop early instead of holding up the shutdown or writing to
+    /// nobody.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Typed values attached to the request, e.g. by a `before_route` hook.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// The app state of type `S` set with `Bobby::with_state`, or `None` if
+    /// there's none of that type.
+    pub fn state<S: Send + Sync + 'static>(&self) -> Option<&S> {
+        self.state.get::<S>()
+    }
+}
+
+// Parses a param or query value, if there is one.
+fn parse_value<T: FromStr>(name: &str, value: Option<&String>) -> Result<T, ValueError<T::Err>> {
+    let value = value.ok_or_else(|| ValueError::Missing(String::from(name)))?;
+
+    value
+        .parse()
+        .map_err(|err| ValueError::Invalid(String::from(name), err))
+}
+
+/// Why a typed param or query value couldn't be had, from
+/// `Request::param_as_result` and `Request::query_as_result`.
+pub enum ValueError<E> {
+    /// There's no value with the given name.
+    Missing(String),
+    /// The value with the given name doesn't parse, with the parse error.
+    Invalid(String, E),
 }
 
+impl<E: std::fmt::Display> std::fmt::Display for ValueError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueError::Missing(name) => write!(f, "Missing \"{}\"", name),
+            ValueError::Invalid(name, err) => write!(f, "Invalid \"{}\": {}", name, err),
+        }
+    }
+}
+
+impl<E: std::fmt::Display> std::fmt::Debug for ValueError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl<E: std::fmt::Display> std::error::Error for ValueError<E> {}
+
 pub enum ResponseError {
     CannotGetHeaders,
     InvalidHeaderName,
@@ -84,43 +427,382 @@ impl std::fmt::Debug for ResponseError {
 
 impl std::error::Error for ResponseError {}
 
-#[derive(Clone)]
 pub struct Response {
-    body: String,
+    body: Body,
     status: u16,
-    headers: HashMap<String, String>,
+    headers: Vec<(String, String)>,
+    cookies: Vec<Cookie>,
+    fragment: bool,
+    // the file `Response::file` opens once the handler has returned
+    #[cfg(feature = "fs")]
+    file: Option<PathBuf>,
 }
 
 impl Response {
     pub fn html(body: impl Into<String>) -> Self {
         Response {
-            body: body.into(),
+            body: Body::Text(body.into()),
             status: 200,
-            headers: HashMap::from([(String::from("Content-Type"), String::from("text/html"))]),
+            headers: vec![(String::from("Content-Type"), String::from("text/html"))],
+            cookies: vec![],
+            fragment: false,
+            #[cfg(feature = "fs")]
+            file: None,
+        }
+    }
+
+    /// An HTML fragment, e.g. for htmx or Turbo, which is sent as is rather
+    /// than wrapped in the layout set with `Bobby::with_layout`.
+    pub fn fragment(body: impl Into<String>) -> Self {
+        Response {
+            fragment: true,
+            ..Response::html(body)
         }
     }
 
-    pub fn with_status(self, status: u16) -> Self {
-        let mut response = self.clone();
+    /// A `text/plain; charset=utf-8` response.
+    pub fn text(body: impl Into<String>) -> Self {
+        Response::html(body).with_content_type("text/plain; charset=utf-8")
+    }
 
-        response.status = status;
+    /// Renders anything that implements `Display` as a `text/plain` response
+    /// with a `200` status, handy for quick handlers.
+    pub fn display(value: impl std::fmt::Display) -> Self {
+        Response::text(value.to_string())
+    }
 
-        response
+    #[cfg(feature = "json")]
+    pub fn json<T: Serialize>(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Response::json_body(serde_json::to_string(value)?))
+    }
+
+    /// Like `json`, but pretty-printed, which is handy for endpoints you'll
+    /// be reading by hand.
+    #[cfg(feature = "json")]
+    pub fn json_pretty<T: Serialize>(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Response::json_body(serde_json::to_string_pretty(value)?))
+    }
+
+    #[cfg(feature = "json")]
+    fn json_body(body: String) -> Self {
+        Response {
+            body: Body::Text(body),
+            status: 200,
+            headers: vec![(
+                String::from("Content-Type"),
+                String::from("application/json"),
+            )],
+            ..Response::html("")
+        }
+    }
+
+    /// A binary response. Falls back to `application/octet-stream` when
+    /// `content_type` is empty or not a valid `type/subtype` media type.
+    pub fn bytes(body: impl Into<Vec<u8>>, content_type: &str) -> Self {
+        let content_type = if is_valid_content_type(content_type) {
+            content_type
+        } else {
+            if !content_type.is_empty() {
+                warn!(
+                    "Invalid content type \"{}\", using application/octet-stream",
+                    content_type
+                );
+            }
+
+            "application/octet-stream"
+        };
+
+        Response {
+            body: Body::from(body.into()),
+            status: 200,
+            headers: vec![(String::from("Content-Type"), String::from(content_type))],
+            ..Response::html("")
+        }
+    }
+
+    /// A response streamed to the client as `stream` yields chunks, sent
+    /// chunked (or as HTTP/2 data frames) rather than with a
+    /// `Content-Length`. It's `application/octet-stream` unless changed with
+    /// `with_content_type`.
+    pub fn stream<S, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<BoxError> + 'static,
+    {
+        Response {
+            body: Body::stream(stream),
+            status: 200,
+            headers: vec![(
+                String::from("Content-Type"),
+                String::from("application/octet-stream"),
+            )],
+            ..Response::html("")
+        }
     }
 
+    /// Streams the file at `path`, with the `Content-Type` guessed from its
+    /// extension and `Content-Length` and `Last-Modified` taken from disk.
+    /// Gives a 404 when there's no such file. The file is opened with
+    /// `tokio::fs` once the handler and middleware have returned, so only
+    /// then does the response get its length, body or 404 status.
+    #[cfg(feature = "fs")]
+    pub fn file(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+
+        Response {
+            body: Body::empty(),
+            file: Some(path.to_path_buf()),
+            ..Response::html("").with_content_type(content_type(path))
+        }
+    }
+
+    // Opens the file left by `Response::file`, without blocking the runtime.
+    #[cfg(feature = "fs")]
+    pub(crate) async fn open_file(mut self) -> Self {
+        let Some(path) = self.file.take() else {
+            return self;
+        };
+
+        let opened = match tokio::fs::File::open(&path).await {
+            Ok(file) => file.metadata().await.map(|metadata| (file, metadata)),
+            Err(err) => Err(err),
+        };
+
+        self.with_opened_file(opened)
+    }
+
+    // The fallback for a file response built by hand with `build`, outside
+    // the router, which has nowhere to await it.
+    #[cfg(feature = "fs")]
+    fn open_file_blocking(mut self) -> Self {
+        let Some(path) = self.file.take() else {
+            return self;
+        };
+
+        let opened = std::fs::File::open(path).and_then(|file| {
+            let metadata = file.metadata()?;
+
+            Ok((tokio::fs::File::from_std(file), metadata))
+        });
+
+        self.with_opened_file(opened)
+    }
+
+    #[cfg(feature = "fs")]
+    fn with_opened_file(
+        self,
+        opened: std::io::Result<(tokio::fs::File, std::fs::Metadata)>,
+    ) -> Self {
+        let (file, metadata) = match opened {
+            Ok((file, metadata)) if metadata.is_file() => (file, metadata),
+            // headers middleware added stay, bar the file's content type
+            _ => {
+                return self
+                    .with_status(404)
+                    .with_content_type("text/html")
+                    .with_body(Body::Text(String::from("Not found.")));
+            }
+        };
+
+        let mut response = self.with_header("Content-Length", metadata.len().to_string());
+
+        if let Ok(modified) = metadata.modified() {
+            response = response.with_header("Last-Modified", httpdate::fmt_http_date(modified));
+        }
+
+        response.with_body(Body::stream(ReaderStream::new(file)))
+    }
+
+    /// A CSV download, with `headers` as the first row followed by `rows` as
+    /// they're produced. Fields are quoted and escaped as needed, and the
+    /// response is sent as an attachment.
+    pub fn csv_stream<S, R>(headers: &[&str], rows: S) -> Self
+    where
+        S: Stream<Item = R> + Send + 'static,
+        R: IntoIterator,
+        R::Item: AsRef<str>,
+    {
+        let header = Bytes::from(csv::record(headers));
+        let rows = rows.map(|row| Ok::<_, BoxError>(Bytes::from(csv::record(row))));
+        let body = stream::once(async move { Ok(header) }).chain(rows);
+
+        Response::html("")
+            .with_content_type("text/csv; charset=utf-8")
+            .with_header("Content-Disposition", "attachment")
+            .with_body(Body::stream(body))
+    }
+
+    /// Marks the response as a download, saved as `filename` by browsers.
+    pub fn with_attachment(self, filename: &str) -> Self {
+        self.with_header(
+            "Content-Disposition",
+            content_disposition("attachment", filename),
+        )
+    }
+
+    /// A response browsers display rather than download, e.g. a PDF or image
+    /// preview, with `filename` suggested for saving it. The `Content-Type`
+    /// is guessed from the file name's extension.
+    pub fn inline(filename: &str, body: impl Into<Body>) -> Self {
+        Response::html("")
+            .with_content_type(content_type(Path::new(filename)))
+            .with_header(
+                "Content-Disposition",
+                content_disposition("inline", filename),
+            )
+            .with_body(body.into())
+    }
+
+    /// A `302 Found` redirect to `location`, with an empty body the layout
+    /// leaves alone. Use `with_status` for a `303`, or a `307` to keep the
+    /// request method.
+    pub fn redirect(location: impl Into<String>) -> Self {
+        Response::fragment("")
+            .with_status(302)
+            .with_header("Location", location)
+    }
+
+    /// A `301 Moved Permanently` redirect to `location`, which browsers and
+    /// caches remember. Use `with_status(308)` to keep the request method.
+    pub fn permanent_redirect(location: impl Into<String>) -> Self {
+        Response::redirect(location).with_status(301)
+    }
+
+    /// Sets `cookie` with a `Set-Cookie` header of its own, so responses can
+    /// set several. A cookie with characters that aren't allowed in its name,
+    /// value or attributes is left out with a warning.
+    pub fn with_cookie(mut self, cookie: Cookie) -> Self {
+        if cookie.is_valid() {
+            self.cookies.push(cookie);
+        } else {
+            warn!("Invalid cookie \"{}\", not setting it", cookie);
+        }
+
+        self
+    }
+
+    pub fn with_content_type(self, content_type: impl Into<String>) -> Self {
+        self.with_header("Content-Type", content_type)
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Sets the status only when `condition` holds, leaving it as is otherwise.
+    pub fn with_status_if(self, condition: bool, status: u16) -> Self {
+        if condition {
+            self.with_status(status)
+        } else {
+            self
+        }
+    }
+
+    /// Sets `Retry-After` to `delay` in whole seconds, rounded up, e.g. for a
+    /// `429` or `503`.
+    pub fn with_retry_after(self, delay: Duration) -> Self {
+        let seconds = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+
+        self.with_header("Retry-After", seconds.to_string())
+    }
+
+    /// Sets `Retry-After` to the HTTP date `time`, for when the moment the
+    /// client may retry is known rather than how long to wait.
+    pub fn with_retry_at(self, time: SystemTime) -> Self {
+        self.with_header("Retry-After", httpdate::fmt_http_date(time))
+    }
+
+    /// Sets the header `key` to `value`, replacing any value it had. The
+    /// same as `set_header`.
     pub fn with_header(self, key: impl Into<String>, value: impl Into<String>) -> Self {
-        let mut response = self.clone();
+        self.set_header(key, value)
+    }
 
-        response.headers.insert(key.into(), value.into());
+    /// Sets the header `key` to `value`, replacing every value it had. Names
+    /// compare case-insensitively.
+    pub fn set_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
 
-        response
+        self.headers
+            .retain(|(name, _)| !name.eq_ignore_ascii_case(&key));
+        self.headers.push((key, value.into()));
+        self
+    }
+
+    // Drops every value of the header `key`.
+    pub(crate) fn without_header(mut self, key: &str) -> Self {
+        self.headers
+            .retain(|(name, _)| !name.eq_ignore_ascii_case(key));
+        self
+    }
+
+    /// Adds `value` to the header `key`, keeping the values it already had,
+    /// for headers that repeat like `Vary` or `Link`. Each value goes out as a
+    /// header line of its own.
+    pub fn append_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((key.into(), value.into()));
+        self
+    }
+
+    /// Adds a `Link: <url>; rel=preload; as=as_type` header, so browsers can
+    /// start fetching the asset before they've parsed the page. Several
+    /// preloads are joined into a single `Link` header.
+    pub fn with_preload(mut self, url: &str, as_type: &str) -> Self {
+        let link = format!("<{}>; rel=preload; as={}", url, as_type);
+        let existing = self
+            .headers
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case("link"));
+
+        match existing {
+            Some((_, value)) => *value = format!("{}, {}", value, link),
+            None => self.headers.push((String::from("Link"), link)),
+        }
+
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Body>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// The first value of the header `name`, looked up case-insensitively.
+    /// Cookies set with `with_cookie` aren't among the headers until the
+    /// response is built.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
     }
 
-    pub fn build(self) -> Result<hyper::Response<String>, ResponseError> {
+    /// Every header as a name and value pair, in the order they were set.
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    pub fn build(self) -> Result<hyper::Response<HttpBody>, ResponseError> {
+        #[cfg(feature = "fs")]
+        if self.file.is_some() {
+            return self.open_file_blocking().build();
+        }
+
         let mut builder = hyper::Response::builder().status(self.status);
         let headers = builder
             .headers_mut()
-            .ok_or_else(|| ResponseError::CannotGetHeaders)?;
+            .ok_or(ResponseError::CannotGetHeaders)?;
 
         // construct headers
         for (k, v) in self.headers.into_iter() {
@@ -130,26 +812,576 @@ impl Response {
             let header_value =
                 header::HeaderValue::from_str(&v).map_err(|_| ResponseError::InvalidHeaderValue)?;
 
-            headers.insert(header_name, header_value);
+            headers.append(header_name, header_value);
         }
 
-        // add content length
-        headers.insert(
-            header::HeaderName::from_static("content-length"),
-            header::HeaderValue::from_str(&self.body.len().to_string())
-                .map_err(|_| ResponseError::FailedToCreateHeader)?,
-        );
+        // one header per cookie, as they can't be joined like other headers
+        for cookie in &self.cookies {
+            let header_value = header::HeaderValue::from_str(&cookie.to_string())
+                .map_err(|_| ResponseError::InvalidHeaderValue)?;
+
+            headers.append(header::SET_COOKIE, header_value);
+        }
+
+        // 204 and 304 responses can't have a body, so drop any that was set
+        let no_body = matches!(self.status, 204 | 304);
+        let mut body = self.body;
+
+        if no_body {
+            if !body.is_empty() {
+                debug!("Dropping the body of a {} response", self.status);
+            }
+
+            body = Body::empty();
+        }
+
+        // add content length, which for text and bytes is always their size
+        // in bytes, whatever the handler set. Streams go out chunked, unless
+        // the handler knows the length and set it once, or consistently
+        if let Some(len) = body.len().filter(|_| !no_body) {
+            let len = len.to_string();
+
+            if let Some(set) = headers.get(header::CONTENT_LENGTH)
+                && set.as_bytes() != len.as_bytes()
+            {
+                debug!("Replacing Content-Length {:?} with the body's {}", set, len);
+            }
+
+            headers.insert(
+                header::CONTENT_LENGTH,
+                header::HeaderValue::from_str(&len)
+                    .map_err(|_| ResponseError::FailedToCreateHeader)?,
+            );
+        } else if no_body {
+            // hyper leaves it out of the GET response, so HEAD matches
+            headers.remove(header::CONTENT_LENGTH);
+        } else if !has_valid_content_length(headers) {
+            warn!("Dropping an invalid Content-Length, sending the body chunked");
+
+            headers.remove(header::CONTENT_LENGTH);
+        }
 
         // add body and return
-        Ok(builder.body(self.body).unwrap())
+        Ok(builder.body(body.into_http_body()).unwrap())
     }
 }
 
+// Whether the `Content-Length` values, if any, are all the same number.
+fn has_valid_content_length(headers: &HeaderMap) -> bool {
+    let mut values = headers.get_all(header::CONTENT_LENGTH).iter();
+
+    let Some(first) = values.next() else {
+        return true;
+    };
+
+    let valid = first
+        .to_str()
+        .is_ok_and(|value| value.parse::<u64>().is_ok());
+
+    valid && values.all(|value| value == first)
+}
+
+impl From<CachedResponse> for Response {
+    fn from(cached: CachedResponse) -> Self {
+        Response {
+            body: Body::Bytes(cached.body),
+            status: cached.status,
+            headers: cached.headers,
+            ..Response::html("")
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<(hyper::StatusCode, serde_json::Value)> for Response {
+    fn from((status, value): (hyper::StatusCode, serde_json::Value)) -> Self {
+        Response::from((status.as_u16(), value))
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<(u16, serde_json::Value)> for Response {
+    fn from((status, value): (u16, serde_json::Value)) -> Self {
+        // a `Value` always serializes, its map keys are strings by construction
+        Response::json(&value).unwrap().with_status(status)
+    }
+}
+
+/// What a route handler can return: a `Response`, or a `Result` whose error
+/// turns into one, so handlers can use `?`.
+pub trait IntoResponse {
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(response) => response.into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+/// An error a handler can return with `?`. Any `std::error::Error` converts
+/// into a `500` that's logged with the error's message but sent with a
+/// generic body, so internals don't leak to clients. Use `HttpError::new` for
+/// errors whose message is meant for the client, like a `400` for bad input.
+/// The body is sent as an HTML fragment, so the layout doesn't wrap it.
+#[derive(Debug)]
+pub struct HttpError {
+    status: u16,
+    message: Option<String>,
+}
+
+impl HttpError {
+    /// An error answered with `status` and `message` as the body.
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        HttpError {
+            status,
+            message: Some(message.into()),
+        }
+    }
+
+    /// An error answered with `status` and its reason phrase as the body,
+    /// e.g. "Not found." for a `404`.
+    pub fn status(status: u16) -> Self {
+        HttpError {
+            status,
+            message: None,
+        }
+    }
+}
+
+impl<E: std::error::Error> From<E> for HttpError {
+    fn from(err: E) -> Self {
+        error!("Handler failed: {}", err);
+
+        HttpError::status(500)
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        let message = self.message.unwrap_or_else(|| {
+            let reason = hyper::StatusCode::from_u16(self.status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or("Error");
+            let mut reason = reason.to_lowercase();
+
+            reason.replace_range(..1, &reason[..1].to_uppercase());
+
+            format!("{}.", reason)
+        });
+
+        Response::fragment(message).with_status(self.status)
+    }
+}
+
+// A `Content-Disposition` value of type `kind` for `filename`. Names outside
+// of printable ASCII go in `filename*`, with a plain fallback.
+fn content_disposition(kind: &str, filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '_',
+        })
+        .collect();
+
+    if fallback == filename {
+        format!("{}; filename=\"{}\"", kind, filename)
+    } else {
+        format!(
+            "{}; filename=\"{}\"; filename*=UTF-8''{}",
+            kind,
+            fallback,
+            percent_encode(filename)
+        )
+    }
+}
+
+// Checks for a `type/subtype` media type, optionally followed by parameters.
+fn is_valid_content_type(content_type: &str) -> bool {
+    let is_token = |s: &str| {
+        !s.is_empty()
+            && s.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+    };
+
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+
+    match essence.split_once('/') {
+        Some((kind, subtype)) => {
+            is_token(kind)
+                && is_token(subtype)
+                && header::HeaderValue::from_str(content_type).is_ok()
+        }
+        None => false,
+    }
+}
+
+// The host the client asked for, from `X-Forwarded-Host` when behind a
+// trusted proxy, then the `Host` header, then the URI authority (HTTP/2).
+fn request_host<'a>(
+    headers: &'a HeaderMap,
+    uri: &'a hyper::Uri,
+    trust_proxy: bool,
+) -> Option<&'a str> {
+    headers
+        .get("x-forwarded-host")
+        .filter(|_| trust_proxy)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .or_else(|| {
+            headers
+                .get(header::HOST)
+                .and_then(|value| value.to_str().ok())
+        })
+        .or(uri.authority().map(|authority| authority.as_str()))
+}
+
+// The method a `POST` can be overridden to, from an `X-HTTP-Method-Override`
+// header or `_method` form field. Only PUT, PATCH and DELETE qualify, since
+// turning a POST into a GET or anything else makes no sense for a form.
+fn overridden_method(value: &str) -> Option<hyper::Method> {
+    match value.trim().to_ascii_uppercase().as_str() {
+        "PUT" => Some(hyper::Method::PUT),
+        "PATCH" => Some(hyper::Method::PATCH),
+        "DELETE" => Some(hyper::Method::DELETE),
+        _ => None,
+    }
+}
+
+// Whether the body is an urlencoded form, going by its `Content-Type`.
+fn is_form(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|essence| {
+            essence
+                .trim()
+                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+        })
+}
+
+// The client's address. Behind a trusted proxy that's `X-Real-IP`, or else
+// the last `X-Forwarded-For` entry, the one the proxy itself added, as the
+// ones before it come from the client and could be made up.
+fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, trust_proxy: bool) -> Option<IpAddr> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .filter(|_| trust_proxy)
+            .and_then(|value| value.to_str().ok())
+    };
+
+    header("x-real-ip")
+        .and_then(|value| value.trim().parse().ok())
+        .or_else(|| {
+            header("x-forwarded-for")
+                .and_then(|value| value.rsplit(',').next())
+                .and_then(|value| value.trim().parse().ok())
+        })
+        .or(peer.map(|peer| peer.ip()))
+}
+
+// Whether the path params captured for `route` satisfy its constraints. An
+// optional param that wasn't given has nothing to check.
+fn constraints_hold(route: &Route, params: &HashMap<String, String>) -> bool {
+    route
+        .constraints
+        .iter()
+        .all(|(name, regex)| params.get(name).is_none_or(|value| regex.is_match(value)))
+}
+
+// Joins a group prefix and a path with exactly one slash between them, and
+// without a trailing one for the group's own root, so `/api` and `/` give
+// `/api`.
+fn join_paths(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    let path = path.trim_start_matches('/');
+
+    match (prefix.is_empty(), path.is_empty()) {
+        (true, _) => format!("/{}", path),
+        (false, true) => String::from(prefix),
+        (false, false) => format!("{}/{}", prefix, path),
+    }
+}
+
+// Fills the request's params into a redirect target pattern and carries the
+// query string over. Params the request doesn't have are left out. Targets
+// can also be absolute URLs, whose scheme and host are kept as they are.
+fn redirect_location(target: &str, req: &Request) -> String {
+    let origin_len = target.find("://").map_or(0, |i| {
+        target[i + 3..]
+            .find('/')
+            .map_or(target.len(), |j| i + 3 + j)
+    });
+
+    let (origin, target) = target.split_at(origin_len);
+    let mut location = String::new();
+
+    for segment in target.split('/').filter(|s| !s.is_empty()) {
+        let param = segment
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .map(|inner| {
+                let inner = inner.trim_start_matches(['*', '+']);
+
+                pattern::param_parts(inner).0
+            });
+
+        match param {
+            Some(name) => {
+                if let Some(value) = req.params.get(name).filter(|value| !value.is_empty()) {
+                    location.push('/');
+                    location.push_str(value);
+                }
+            }
+            None => {
+                location.push('/');
+                location.push_str(segment);
+            }
+        }
+    }
+
+    if location.is_empty() || target.ends_with('/') {
+        location.push('/');
+    }
+
+    if let Some(query) = req.uri.query() {
+        location.push('?');
+        location.push_str(query);
+    }
+
+    format!("{}{}", origin, location)
+}
+
+// The route pattern (not the concrete path) a response was produced by,
+// carried in the response extensions for timing and logging.
 #[derive(Clone)]
-pub struct Route {
+struct MatchedPath(String);
+
+// Cancelled on shutdown, or when the client goes away before the response
+// was sent in full.
+#[derive(Clone)]
+struct RequestCancellation(CancellationToken);
+
+// The route a request resolved to, along with the method and URI it was
+// resolved for, so it's only looked up again when a hook or a `_method`
+// field changes either.
+struct Resolved<'a> {
     method: hyper::Method,
-    path: String,
-    callable: fn(req: Request) -> Response,
+    uri: hyper::Uri,
+    route: Option<(&'a Route, HashMap<String, String>)>,
+}
+
+// The address of the client connection a request came in on.
+#[derive(Clone, Copy)]
+struct PeerAddr(SocketAddr);
+
+#[derive(Clone)]
+pub struct Route {
+    pub(crate) method: hyper::Method,
+    pub(crate) path: String,
+    callable: Handler,
+    #[cfg(feature = "json")]
+    pub(crate) summary: Option<String>,
+    cache_ttl: Option<Duration>,
+    stream_body: bool,
+    host: Option<String>,
+    pub(crate) redirect: Option<(String, u16)>,
+    enabled: Arc<AtomicBool>,
+    constraints: Vec<(String, Regex)>,
+    specificity: Vec<u8>,
+    timeout: Option<Duration>,
+}
+
+impl Route {
+    /// A short summary of what the route does, used in the OpenAPI spec.
+    #[cfg(feature = "json")]
+    pub fn describe(&mut self, summary: impl Into<String>) -> &mut Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    /// Caches successful GET responses of this route in memory for `ttl`,
+    /// keyed by path and query, so repeat requests skip the handler.
+    pub fn cache(&mut self, ttl: Duration) -> &mut Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Only matches requests for hosts matching `pattern`, e.g.
+    /// `{tenant}.example.com`. Params in the pattern capture a single label
+    /// each and are available with `Request::param` alongside path params.
+    pub fn host(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.host = Some(pattern.into());
+        self
+    }
+
+    /// Leaves the request body unread so the handler can consume it
+    /// incrementally with `Request::into_body_stream`, instead of Bobby
+    /// buffering it first.
+    pub fn stream_body(&mut self) -> &mut Self {
+        self.stream_body = true;
+        self
+    }
+
+    /// Gives up on requests to this route after `timeout`, instead of the
+    /// app's `with_request_timeout`.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+// A route handler, boxed so closures capturing state work as well as plain
+// functions.
+pub(crate) type Handler = Arc<dyn Fn(Request) -> Response + Send + Sync>;
+
+// Boxes `callable` as a `Handler`, whatever it returns that turns into a
+// response.
+fn handler<R: IntoResponse>(callable: impl Fn(Request) -> R + Send + Sync + 'static) -> Handler {
+    Arc::new(move |req| callable(req).into_response())
+}
+
+// A hook run on requests before they're matched, added with
+// `Bobby::before_route`.
+pub(crate) type BeforeRoute = Arc<dyn Fn(&mut Request) + Send + Sync>;
+
+// A middleware, wrapped around the handler with `Bobby::middleware`.
+pub(crate) type Middleware = Arc<dyn Fn(Request, Next) -> Response + Send + Sync>;
+
+/// The rest of the middleware chain, ending in what answers the request.
+pub struct Next<'a> {
+    middleware: &'a [Middleware],
+    endpoint: Box<dyn FnOnce(Request) -> Response + Send + 'a>,
+}
+
+impl Next<'_> {
+    /// Passes the request on down the chain and returns its response.
+    pub fn run(self, req: Request) -> Response {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => middleware(
+                req,
+                Next {
+                    middleware: rest,
+                    endpoint: self.endpoint,
+                },
+            ),
+            None => (self.endpoint)(req),
+        }
+    }
+}
+
+// What answers a request once it's been matched.
+enum Endpoint<'a> {
+    Route(&'a Route, HashMap<String, String>),
+    // the spec, echo endpoint, static files and trailing slash redirects
+    Ready(Response),
+    // with the `Allow` header value
+    MethodNotAllowed(String),
+    NotFound,
+}
+
+pub type Layout = Arc<dyn Fn(String) -> String + Send + Sync>;
+
+pub type RequestTimingHook = Arc<dyn Fn(Option<&str>, &hyper::Method, u16, Duration) + Send + Sync>;
+
+pub type ConnectionCloseHook = Arc<dyn Fn(SocketAddr, u64, Duration) + Send + Sync>;
+
+/// Handlers for the methods of a single path, registered together with
+/// `Bobby::resource`. Methods without a handler aren't registered.
+#[derive(Clone, Default)]
+pub struct Resource {
+    get: Option<Handler>,
+    post: Option<Handler>,
+    put: Option<Handler>,
+    delete: Option<Handler>,
+    patch: Option<Handler>,
+    options: Option<Handler>,
+    head: Option<Handler>,
+}
+
+impl Resource {
+    pub fn new() -> Self {
+        Resource::default()
+    }
+
+    pub fn get<R: IntoResponse>(
+        mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> Self {
+        self.get = Some(handler(callable));
+        self
+    }
+
+    pub fn post<R: IntoResponse>(
+        mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> Self {
+        self.post = Some(handler(callable));
+        self
+    }
+
+    pub fn put<R: IntoResponse>(
+        mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> Self {
+        self.put = Some(handler(callable));
+        self
+    }
+
+    pub fn delete<R: IntoResponse>(
+        mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> Self {
+        self.delete = Some(handler(callable));
+        self
+    }
+
+    pub fn patch<R: IntoResponse>(
+        mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> Self {
+        self.patch = Some(handler(callable));
+        self
+    }
+
+    pub fn options<R: IntoResponse>(
+        mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> Self {
+        self.options = Some(handler(callable));
+        self
+    }
+
+    pub fn head<R: IntoResponse>(
+        mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> Self {
+        self.head = Some(handler(callable));
+        self
+    }
+}
+
+#[derive(Clone)]
+enum ServerHeader {
+    Unset,
+    Set(header::HeaderValue),
+    Suppressed,
 }
 
 #[derive(Clone)]
@@ -157,6 +1389,60 @@ pub struct Bobby {
     ip: IpAddr,
     port: u16,
     routes: Vec<Route>,
+    route_index: RouteIndex,
+    route_prefix: String,
+    max_routes: Option<usize>,
+    route_conflicts: RouteConflict,
+    trailing_slash: TrailingSlash,
+    #[cfg(feature = "fs")]
+    static_files: Vec<StaticFiles>,
+    before_route: Vec<BeforeRoute>,
+    middleware: Vec<Middleware>,
+    // whether a `cors` middleware answers preflight requests
+    cors: bool,
+    on_request_timing: Option<RequestTimingHook>,
+    on_connection_close: Option<ConnectionCloseHook>,
+    #[cfg(feature = "server")]
+    on_connect: Option<ConnectHook>,
+    #[cfg(feature = "tls")]
+    tls: Option<tokio_rustls::TlsAcceptor>,
+    access_log: Option<AccessLogFormat>,
+    request_log: bool,
+    request_timeout: Option<Duration>,
+    layout: Option<Layout>,
+    #[cfg(feature = "json")]
+    openapi: Option<OpenApi>,
+    #[cfg(feature = "json")]
+    echo_path: Option<String>,
+    not_found_body: String,
+    not_found_status_text: Option<ReasonPhrase>,
+    not_found: Option<Handler>,
+    method_not_allowed: Option<Handler>,
+    idle_timeout: Duration,
+    max_accept_backoff: Duration,
+    http2_max_concurrent_streams: Option<u32>,
+    http2_max_pending_reset_streams: Option<usize>,
+    max_body_size: usize,
+    #[cfg(feature = "json")]
+    json_limits: JsonLimits,
+    trust_proxy: bool,
+    reuse_port: bool,
+    strict_query: bool,
+    method_override: bool,
+    worker_threads: Option<usize>,
+    shutdown: CancellationToken,
+    maintenance: Arc<AtomicBool>,
+    maintenance_allow: Vec<String>,
+    maintenance_retry_after: Duration,
+    state: Arc<Extensions>,
+    response_cache: Arc<Mutex<ResponseCache>>,
+    server_header: ServerHeader,
+}
+
+impl Default for Bobby {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Bobby {
@@ -165,73 +1451,683 @@ impl Bobby {
             ip: IpAddr::from([127, 0, 0, 1]),
             port: 8080,
             routes: vec![],
+            route_index: RouteIndex::default(),
+            route_prefix: String::new(),
+            max_routes: None,
+            route_conflicts: RouteConflict::Warn,
+            trailing_slash: TrailingSlash::Relaxed,
+            #[cfg(feature = "fs")]
+            static_files: vec![],
+            before_route: vec![],
+            middleware: vec![],
+            cors: false,
+            on_request_timing: None,
+            on_connection_close: None,
+            #[cfg(feature = "server")]
+            on_connect: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+            access_log: None,
+            request_log: true,
+            request_timeout: None,
+            layout: None,
+            #[cfg(feature = "json")]
+            openapi: None,
+            #[cfg(feature = "json")]
+            echo_path: None,
+            not_found_body: String::from("Not found."),
+            not_found_status_text: None,
+            not_found: None,
+            method_not_allowed: None,
+            idle_timeout: Duration::from_secs(60),
+            max_accept_backoff: Duration::from_secs(1),
+            http2_max_concurrent_streams: None,
+            http2_max_pending_reset_streams: None,
+            max_body_size: 2 * 1024 * 1024,
+            #[cfg(feature = "json")]
+            json_limits: JsonLimits::default(),
+            trust_proxy: false,
+            reuse_port: false,
+            strict_query: false,
+            method_override: false,
+            worker_threads: None,
+            shutdown: CancellationToken::new(),
+            maintenance: Arc::new(AtomicBool::new(false)),
+            maintenance_allow: vec![],
+            maintenance_retry_after: Duration::from_secs(120),
+            state: Arc::new(Extensions::new()),
+            response_cache: Arc::new(Mutex::new(ResponseCache::new(1000))),
+            server_header: ServerHeader::Unset,
         }
     }
 
-    pub fn with_address(&mut self, ip: impl Into<IpAddr>, port: u16) {
+    /// A new app listening on `address`, e.g. `"0.0.0.0:3000"` or
+    /// `"[::1]:8080"`.
+    pub fn bind(address: &str) -> Result<Bobby, AddrParseError> {
+        let address: SocketAddr = address.parse()?;
+        let mut app = Bobby::new();
+
+        app.with_address(address.ip(), address.port());
+
+        Ok(app)
+    }
+
+    pub fn with_address(&mut self, ip: impl Into<IpAddr>, port: u16) -> &mut Self {
         self.ip = ip.into();
         self.port = port;
+        self
+    }
+
+    /// Sets how many worker threads the runtime started by `run` uses.
+    /// Defaults to one per CPU core. Passing `0` runs everything on the
+    /// thread that called `run` instead.
+    pub fn with_worker_threads(&mut self, threads: usize) -> &mut Self {
+        self.worker_threads = Some(threads);
+        self
+    }
+
+    /// Trusts the `X-Forwarded-*` headers set by a reverse proxy in front of
+    /// the app. Only enable this when clients can't reach Bobby directly, as
+    /// otherwise they can set these headers to whatever they like.
+    pub fn with_trusted_proxy(&mut self, trusted: bool) -> &mut Self {
+        self.trust_proxy = trusted;
+        self
+    }
+
+    /// Sets `SO_REUSEPORT` on the listening socket, so a new process can bind
+    /// the same port while the old one drains, with the kernel spreading
+    /// connections between them. Only has an effect on Unix.
+    pub fn with_reuse_port(&mut self, enabled: bool) -> &mut Self {
+        self.reuse_port = enabled;
+        self
+    }
+
+    /// Routes `POST` requests as the method in their `X-HTTP-Method-Override`
+    /// header, or else the `_method` field of their urlencoded form body, so
+    /// plain HTML forms can reach `PUT`, `PATCH` and `DELETE` routes. Other
+    /// values are ignored. Off by default.
+    pub fn with_method_override(&mut self, enabled: bool) -> &mut Self {
+        self.method_override = enabled;
+        self
+    }
+
+    /// Rejects requests whose query string has malformed percent-encoding
+    /// (`%zz`, a truncated `%a`) or isn't valid UTF-8 with a `400`. By
+    /// default such sequences are left as written.
+    pub fn with_strict_query(&mut self, strict: bool) -> &mut Self {
+        self.strict_query = strict;
+        self
+    }
+
+    /// Logs every request once its response is ready, in `format`, through
+    /// the `log` crate with the `bobby::access` target.
+    pub fn with_access_log(&mut self, format: AccessLogFormat) -> &mut Self {
+        self.access_log = Some(format);
+        self
+    }
+
+    /// Turns the line logged at the `info` level as each request comes in,
+    /// like `HTTP/1.1 GET /`, on or off. On by default. Warnings about bad
+    /// requests are logged either way.
+    pub fn with_request_log(&mut self, enabled: bool) -> &mut Self {
+        self.request_log = enabled;
+        self
+    }
+
+    /// Answers requests that take longer than `timeout` to produce a
+    /// response, reading the body included, with a `503`. Routes can set
+    /// their own with `Route::timeout`. Off by default.
+    pub fn with_request_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Closes connections that have neither sent nor received anything for
+    /// `timeout`. Defaults to 60 seconds.
+    pub fn with_idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Caps how long the server waits before accepting again after accepting
+    /// a connection failed, e.g. because the process ran out of file
+    /// descriptors. The wait starts at 10ms and doubles with every failure in
+    /// a row, up to `max`. Defaults to 1 second.
+    pub fn with_max_accept_backoff(&mut self, max: Duration) -> &mut Self {
+        self.max_accept_backoff = max;
+        self
+    }
+
+    /// Caps how many requests a single HTTP/2 connection can have in flight
+    /// at once. Defaults to hyper's limit, currently 200.
+    pub fn with_http2_max_concurrent_streams(&mut self, max: u32) -> &mut Self {
+        self.http2_max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Caps how many streams an HTTP/2 client may reset before Bobby has even
+    /// looked at them, after which the connection is closed with `GOAWAY`.
+    /// This is the guard against rapid reset floods (CVE-2023-44487), and
+    /// defaults to h2's limit, currently 20.
+    pub fn with_http2_max_pending_reset_streams(&mut self, max: usize) -> &mut Self {
+        self.http2_max_pending_reset_streams = Some(max);
+        self
+    }
+
+    /// Sets the largest request body, in bytes, that Bobby accepts. Requests
+    /// declaring a bigger `Content-Length` get a 413. Defaults to 2 MiB.
+    pub fn with_max_body_size(&mut self, size: usize) -> &mut Self {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Sets how deeply nested arrays and objects may be in JSON bodies parsed
+    /// with `Request::json`. Defaults to 64.
+    #[cfg(feature = "json")]
+    pub fn with_max_json_depth(&mut self, depth: usize) -> &mut Self {
+        self.json_limits.max_depth = depth;
+        self
+    }
+
+    /// Sets how many values (array elements, object members and containers)
+    /// JSON bodies parsed with `Request::json` may have. Defaults to 100,000.
+    #[cfg(feature = "json")]
+    pub fn with_max_json_values(&mut self, values: usize) -> &mut Self {
+        self.json_limits.max_values = values;
+        self
+    }
+
+    /// Sets how many responses routes with `Route::cache` keep in memory in
+    /// total, evicting the least recently used first. Defaults to 1,000.
+    pub fn with_cache_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.response_cache.lock().unwrap().set_capacity(capacity);
+        self
+    }
+
+    /// Sends `Server: <name>` with every response, including Bobby's own
+    /// error responses, unless a handler set its own. `None` strips the
+    /// header from all responses instead, handler-set ones included.
+    pub fn with_server_header(&mut self, name: Option<&str>) -> &mut Self {
+        self.server_header = match name {
+            Some(name) => match header::HeaderValue::from_str(name) {
+                Ok(value) => ServerHeader::Set(value),
+                Err(_) => {
+                    warn!("Invalid Server header \"{}\", ignoring it.", name);
+                    ServerHeader::Unset
+                }
+            },
+            None => ServerHeader::Suppressed,
+        };
+        self
+    }
+
+    /// Sets the HTML body sent when no route matches the request.
+    pub fn not_found_body(&mut self, body: impl Into<String>) -> &mut Self {
+        self.not_found_body = body.into();
+        self
+    }
+
+    /// Sets the reason phrase sent with the 404 status line (HTTP/1 only),
+    /// e.g. `HTTP/1.1 404 Nothing Here`. Text that can't go in a status line
+    /// is ignored, keeping the standard `Not Found`.
+    pub fn not_found_status_text(&mut self, text: impl Into<String>) -> &mut Self {
+        let text = text.into();
+
+        self.not_found_status_text = match ReasonPhrase::try_from(text.as_bytes()) {
+            Ok(reason) => Some(reason),
+            Err(_) => {
+                warn!("Invalid 404 status text \"{}\", ignoring it.", text);
+                None
+            }
+        };
+        self
+    }
+
+    /// Answers requests no route matches with `callable` instead of the
+    /// `not_found_body` page, e.g. with a JSON error for an API. The response
+    /// is sent as the handler returns it, so give it a 404 status yourself.
+    /// Like route handlers, it can return a `Result` and use `?`.
+    pub fn not_found<R: IntoResponse>(
+        &mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.not_found = Some(handler(callable));
+        self
+    }
+
+    /// Answers requests whose path is routed, but not for their method, with
+    /// `callable` instead of the built-in 405. Give the response a 405 status
+    /// yourself; the `Allow` header is added unless the handler sets one.
+    pub fn method_not_allowed<R: IntoResponse>(
+        &mut self,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.method_not_allowed = Some(handler(callable));
+        self
+    }
+
+    /// Caps the number of routes that can be registered, as a safeguard for
+    /// apps that register routes dynamically. Unlimited by default.
+    pub fn with_max_routes(&mut self, max: usize) -> &mut Self {
+        self.max_routes = Some(max);
+        self
+    }
+
+    /// Sets what happens when a route is registered that's just as specific
+    /// as an earlier one for the same method, which would always match
+    /// first. Defaults to `RouteConflict::Warn`.
+    pub fn with_route_conflicts(&mut self, policy: RouteConflict) -> &mut Self {
+        self.route_conflicts = policy;
+        self
+    }
+
+    /// Sets whether `/users` and `/users/` are the same path to the routes.
+    /// Defaults to `TrailingSlash::Relaxed`, where they are. Routes ending
+    /// in a wildcard see the slash as part of what they capture either way.
+    pub fn with_trailing_slash(&mut self, mode: TrailingSlash) -> &mut Self {
+        self.trailing_slash = mode;
+        self
+    }
+
+    /// Shares `state` with every handler, which gets it with
+    /// `Request::state::<S>()`. There's one slot per type, so setting the
+    /// same type again replaces it; wrap values in your own types to keep
+    /// several of the same kind apart.
+    pub fn with_state<S: Clone + Send + Sync + 'static>(&mut self, state: S) -> &mut Self {
+        Arc::make_mut(&mut self.state).insert(state);
+        self
+    }
+
+    pub fn get<R: IntoResponse>(
+        &mut self,
+        path: impl Into<String>,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Route {
+        self.push_route(hyper::Method::GET, path.into(), callable)
+    }
+
+    pub fn post<R: IntoResponse>(
+        &mut self,
+        path: impl Into<String>,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Route {
+        self.push_route(hyper::Method::POST, path.into(), callable)
+    }
+
+    pub fn put<R: IntoResponse>(
+        &mut self,
+        path: impl Into<String>,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Route {
+        self.push_route(hyper::Method::PUT, path.into(), callable)
+    }
+
+    pub fn delete<R: IntoResponse>(
+        &mut self,
+        path: impl Into<String>,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Route {
+        self.push_route(hyper::Method::DELETE, path.into(), callable)
+    }
+
+    pub fn patch<R: IntoResponse>(
+        &mut self,
+        path: impl Into<String>,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Route {
+        self.push_route(hyper::Method::PATCH, path.into(), callable)
+    }
+
+    pub fn options<R: IntoResponse>(
+        &mut self,
+        path: impl Into<String>,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Route {
+        self.push_route(hyper::Method::OPTIONS, path.into(), callable)
+    }
+
+    pub fn head<R: IntoResponse>(
+        &mut self,
+        path: impl Into<String>,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Route {
+        self.push_route(hyper::Method::HEAD, path.into(), callable)
+    }
+
+    /// Redirects `GET` and `HEAD` requests for the `from` pattern to the `to`
+    /// pattern with `status`, e.g. 301, for renamed routes. Params of `from`
+    /// are filled into the same-named params of `to`, and the query string is
+    /// kept. Panics on a malformed `from` or a `status` that isn't a `3xx`.
+    pub fn redirect_route(&mut self, from: impl Into<String>, to: impl Into<String>, status: u16) {
+        let (from, to) = (from.into(), to.into());
+        let description = format!("{} -> {}", from, to);
+
+        if let Err(err) = self.try_redirect_route(from, to, status) {
+            panic!("Cannot register redirect {}: {}", description, err);
+        }
+    }
+
+    /// Registers a redirect like `redirect_route`, but returns the problem
+    /// instead of panicking.
+    pub fn try_redirect_route(
+        &mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        status: u16,
+    ) -> Result<(), RouteError> {
+        if !(300..400).contains(&status) {
+            return Err(RouteError::InvalidRedirectStatus(status));
+        }
+
+        let (from, to) = (from.into(), to.into());
+
+        // a path within the app is as much inside the group as `from` is,
+        // while absolute URLs point elsewhere
+        let to = if to.starts_with('/') {
+            self.prefixed(to)
+        } else {
+            to
+        };
+
+        for method in [hyper::Method::GET, hyper::Method::HEAD] {
+            self.try_route(method, from.clone(), |_| Response::html(""))?
+                .redirect = Some((to.clone(), status));
+        }
+
+        Ok(())
+    }
+
+    pub fn resource(&mut self, path: impl Into<String>, resource: Resource) {
+        let path = path.into();
+        let handlers = [
+            (hyper::Method::GET, resource.get),
+            (hyper::Method::POST, resource.post),
+            (hyper::Method::PUT, resource.put),
+            (hyper::Method::DELETE, resource.delete),
+            (hyper::Method::PATCH, resource.patch),
+            (hyper::Method::OPTIONS, resource.options),
+            (hyper::Method::HEAD, resource.head),
+        ];
+
+        for (method, callable) in handlers {
+            if let Some(callable) = callable {
+                self.push_route(method, path.clone(), move |req| callable(req));
+            }
+        }
+    }
+
+    /// Registers a route for any method, checking the pattern first. Unlike
+    /// `get`, `post` and friends, which panic on a malformed pattern, this
+    /// returns the problem, for routes that come from config or plugins.
+    pub fn try_route<R: IntoResponse>(
+        &mut self,
+        method: hyper::Method,
+        path: impl Into<String>,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> Result<&mut Route, RouteError> {
+        let path = pattern::normalize(&self.prefixed(path.into()));
+
+        if let Some(max) = self.max_routes
+            && self.routes.len() >= max
+        {
+            return Err(RouteError::TooManyRoutes(max));
+        }
+
+        pattern::validate(&path)?;
+
+        let constraints = pattern::constraints(&path)?;
+
+        // an earlier route without a host scope matches any host first, so
+        // only those can make this one unreachable
+        if self.route_conflicts != RouteConflict::Allow {
+            let shape = pattern::shape(&path);
+
+            if let Some(existing) = self.routes.iter().find(|route| {
+                route.method == method
+                    && route.host.is_none()
+                    && pattern::shape(&route.path) == shape
+                    && (self.trailing_slash == TrailingSlash::Relaxed
+                        || pattern::has_trailing_slash(&route.path)
+                            == pattern::has_trailing_slash(&path))
+            }) {
+                if self.route_conflicts == RouteConflict::Reject {
+                    return Err(RouteError::Conflict(existing.path.clone()));
+                }
+
+                warn!(
+                    "Route {} {} is as specific as {}, registered before, which always matches first.",
+                    method, path, existing.path
+                );
+            }
+        }
+
+        let specificity = pattern::specificity(&path);
+
+        self.route_index.insert(self.routes.len(), &path);
+        self.routes.push(Route {
+            method,
+            path,
+            callable: handler(callable),
+            #[cfg(feature = "json")]
+            summary: None,
+            cache_ttl: None,
+            stream_body: false,
+            host: None,
+            redirect: None,
+            enabled: Arc::new(AtomicBool::new(true)),
+            constraints,
+            specificity,
+            timeout: None,
+        });
+
+        Ok(self.routes.last_mut().unwrap())
+    }
+
+    /// Registers the routes added in `routes` under `prefix`, so that
+    /// `group("/api", |g| { g.get("/users", handler); })` adds
+    /// `GET /api/users`. Groups nest, each adding its prefix to the ones
+    /// around it.
+    pub fn group(&mut self, prefix: &str, routes: impl FnOnce(&mut Bobby)) {
+        let outer = self.route_prefix.clone();
+
+        self.route_prefix = join_paths(&outer, prefix);
+        routes(self);
+        self.route_prefix = outer;
+    }
+
+    // `path` under the prefix of the groups it's registered in, if any.
+    fn prefixed(&self, path: String) -> String {
+        if self.route_prefix.is_empty() {
+            path
+        } else {
+            join_paths(&self.route_prefix, &path)
+        }
+    }
+
+    fn push_route<R: IntoResponse>(
+        &mut self,
+        method: hyper::Method,
+        path: String,
+        callable: impl Fn(Request) -> R + Send + Sync + 'static,
+    ) -> &mut Route {
+        let description = format!("{} {}", method, path);
+
+        match self.try_route(method, path, callable) {
+            Ok(route) => route,
+            Err(err) => panic!("Cannot register route {}: {}", description, err),
+        }
+    }
+
+    /// Removes the route registered for `method` and the `path` pattern,
+    /// returning whether there was one. This only affects the app it's called
+    /// on, so use `disable_route` to switch a route off while running.
+    pub fn remove_route(&mut self, method: hyper::Method, path: &str) -> bool {
+        let path = pattern::normalize(path);
+        let len = self.routes.len();
+
+        self.routes
+            .retain(|route| route.method != method || route.path != path);
+        self.route_index = RouteIndex::new(self.routes.iter().map(|route| route.path.as_str()));
+
+        self.routes.len() != len
+    }
+
+    /// Switches off the route registered for `method` and the `path`
+    /// pattern, so requests fall through as if it wasn't registered, and
+    /// returns whether there was one. Like `set_maintenance`, this can be
+    /// called on a clone of the app while it's running.
+    pub fn disable_route(&self, method: hyper::Method, path: &str) -> bool {
+        self.set_route_enabled(method, path, false)
+    }
+
+    /// Switches a route turned off with `disable_route` back on.
+    pub fn enable_route(&self, method: hyper::Method, path: &str) -> bool {
+        self.set_route_enabled(method, path, true)
+    }
+
+    fn set_route_enabled(&self, method: hyper::Method, path: &str, enabled: bool) -> bool {
+        let path = pattern::normalize(path);
+        let mut found = false;
+
+        for route in &self.routes {
+            if route.method == method && route.path == path {
+                route.enabled.store(enabled, Ordering::Relaxed);
+                found = true;
+            }
+        }
+
+        found
     }
 
-    pub fn get(&mut self, path: impl Into<String>, callable: fn(req: Request) -> Response) {
-        self.routes.push(Route {
-            method: hyper::Method::GET,
+    /// Serves an OpenAPI 3 spec of the registered routes at `path`.
+    #[cfg(feature = "json")]
+    pub fn with_openapi(
+        &mut self,
+        path: impl Into<String>,
+        title: impl Into<String>,
+        version: impl Into<String>,
+    ) -> &mut Self {
+        self.openapi = Some(OpenApi {
             path: path.into(),
-            callable,
+            title: title.into(),
+            version: version.into(),
         });
+        self
     }
 
-    pub fn post(&mut self, path: impl Into<String>, callable: fn(req: Request) -> Response) {
-        self.routes.push(Route {
-            method: hyper::Method::POST,
-            path: path.into(),
-            callable,
-        });
+    /// Answers every request to `path`, whatever the method, with what was
+    /// received: the method, URI, headers, query and body as JSON. Meant for
+    /// debugging clients, so leave it off in production.
+    #[cfg(feature = "json")]
+    pub fn with_echo_endpoint(&mut self, path: impl Into<String>) -> &mut Self {
+        self.echo_path = Some(path.into());
+        self
     }
 
-    pub fn put(&mut self, path: impl Into<String>, callable: fn(req: Request) -> Response) {
-        self.routes.push(Route {
-            method: hyper::Method::PUT,
-            path: path.into(),
-            callable,
-        });
+    /// Generates an OpenAPI 3 spec with the registered routes, their path
+    /// params and summaries set with `Route::describe`.
+    #[cfg(feature = "json")]
+    pub fn openapi_spec(&self, title: &str, version: &str) -> serde_json::Value {
+        openapi::spec(&self.routes, title, version)
     }
 
-    pub fn delete(&mut self, path: impl Into<String>, callable: fn(req: Request) -> Response) {
-        self.routes.push(Route {
-            method: hyper::Method::DELETE,
-            path: path.into(),
-            callable,
-        });
+    /// Lists the registered routes as method and path pattern pairs, in
+    /// registration order.
+    pub fn routes(&self) -> Vec<(hyper::Method, &str)> {
+        self.routes
+            .iter()
+            .map(|route| (route.method.clone(), route.path.as_str()))
+            .collect()
     }
 
-    pub fn patch(&mut self, path: impl Into<String>, callable: fn(req: Request) -> Response) {
-        self.routes.push(Route {
-            method: hyper::Method::PATCH,
-            path: path.into(),
-            callable,
-        });
+    /// Wraps the HTML responses of routes in `layout`, which gets the
+    /// response body and returns the full page. Responses made with
+    /// `Response::fragment` are left alone.
+    pub fn with_layout(
+        &mut self,
+        layout: impl Fn(String) -> String + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.layout = Some(Arc::new(layout));
+        self
     }
 
-    pub fn options(&mut self, path: impl Into<String>, callable: fn(req: Request) -> Response) {
-        self.routes.push(Route {
-            method: hyper::Method::OPTIONS,
-            path: path.into(),
-            callable,
-        });
+    /// Runs `hook` on every request before it's matched against the routes,
+    /// in registration order. Hooks can rewrite the method and URI used for
+    /// matching, and attach values to the request's extensions.
+    pub fn before_route(
+        &mut self,
+        hook: impl Fn(&mut Request) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.before_route.push(Arc::new(hook));
+        self
     }
 
-    pub fn head(&mut self, path: impl Into<String>, callable: fn(req: Request) -> Response) {
-        self.routes.push(Route {
-            method: hyper::Method::HEAD,
-            path: path.into(),
-            callable,
-        });
+    /// Wraps every request in `middleware`, which gets the request and the
+    /// rest of the chain. It can call `next.run(req)` and change the
+    /// response on its way out, or answer by itself without calling it.
+    /// Middleware runs in registration order, after the `before_route` hooks
+    /// and matching, and also for requests that end in a 404 or 405.
+    pub fn middleware(
+        &mut self,
+        middleware: impl Fn(Request, Next) -> Response + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Answers CORS preflight requests and adds the `Access-Control-Allow-*`
+    /// headers to responses for the origins `cors` allows. It's middleware,
+    /// so it only covers the middleware added after it.
+    ///
+    /// Panics if `cors` allows any origin along with credentials, which
+    /// would let every site make requests with the user's cookies.
+    pub fn cors(&mut self, cors: Cors) -> &mut Self {
+        if cors.any_origin() && cors.credentials() {
+            panic!("Cannot allow credentials from any origin, list the origins instead");
+        }
+
+        self.cors = true;
+        self.middleware(move |req, next| cors.handle(req, next))
+    }
+
+    /// Calls `hook` after every request with the matched route pattern
+    /// (`None` when nothing matched), the method, the response status and
+    /// how long it took to produce the response.
+    pub fn on_request_timing(
+        &mut self,
+        hook: impl Fn(Option<&str>, &hyper::Method, u16, Duration) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_request_timing = Some(Arc::new(hook));
+        self
+    }
+
+    /// Calls `hook` whenever a connection closes, with the client's address,
+    /// how many requests were served over the connection and how long it was
+    /// open. Counts above one mean keep-alive (or HTTP/2 multiplexing) is
+    /// doing its job.
+    pub fn on_connection_close(
+        &mut self,
+        hook: impl Fn(SocketAddr, u64, Duration) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_connection_close = Some(Arc::new(hook));
+        self
+    }
+
+    /// Serves files from `dir` for GET and HEAD requests under `prefix`,
+    /// e.g. `/assets/app.css` from `public/app.css`. Inside a `group`, the
+    /// group's prefix goes in front.
+    #[cfg(feature = "fs")]
+    pub fn static_files(&mut self, prefix: &str, dir: impl Into<PathBuf>) -> &mut StaticFiles {
+        let prefix = self.prefixed(String::from(prefix));
+
+        self.static_files
+            .push(StaticFiles::new(&prefix, dir.into()));
+        self.static_files.last_mut().unwrap()
     }
 
-    fn log_request(
+    fn log_request<B>(
         &self,
-        request: &hyper::Request<hyper::body::Incoming>,
+        request: &hyper::Request<B>,
         level: log::Level,
         message: impl Into<String>,
     ) {
@@ -281,19 +2177,25 @@ impl Bobby {
     }
 
     fn uri_matches_path(&self, uri: &hyper::Uri, path: &str) -> bool {
-        let path_parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut path_parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
         let uri_parts: Vec<&str> = uri.path().split('/').filter(|s| !s.is_empty()).collect();
+        let wildcard = path_parts.last().and_then(|part| self.parse_wildcard(part));
+
+        if wildcard.is_some() {
+            path_parts.pop();
+        }
 
-        if uri_parts.len() > path_parts.len() {
+        if wildcard.is_none() && uri_parts.len() > path_parts.len() {
             return false;
         }
 
         for (i, path_part) in path_parts.iter().enumerate() {
             let is_param = path_part.starts_with('{') && path_part.ends_with('}');
-            let is_optional_param = is_param && path_part.ends_with("?}");
+            let is_optional_param =
+                is_param && pattern::param_parts(&path_part[1..path_part.len() - 1]).1;
 
             if i >= uri_parts.len() {
-                return is_optional_param;
+                return is_optional_param && wildcard.is_none();
             }
 
             if !is_param && uri_parts[i] != *path_part {
@@ -305,32 +2207,824 @@ impl Bobby {
             }
         }
 
+        if let Some((_, required)) = wildcard {
+            let rest = self.wildcard_rest(uri.path(), path_parts.len());
+
+            return !required || rest.is_some_and(|rest| !rest.is_empty());
+        }
+
         uri_parts.len() <= path_parts.len()
     }
 
-    fn route(
+    // Whether the request path and the route pattern agree on ending in a
+    // slash, when that matters. A trailing wildcard's rest takes the slash.
+    fn trailing_slash_matches(&self, uri_path: &str, path: &str) -> bool {
+        self.trailing_slash == TrailingSlash::Relaxed
+            || path
+                .rsplit('/')
+                .next()
+                .is_some_and(|last| self.parse_wildcard(last).is_some())
+            || pattern::has_trailing_slash(uri_path) == pattern::has_trailing_slash(path)
+    }
+
+    // Parses a trailing wildcard segment, `{*name}` or `{+name}`, into its
+    // name and whether it requires a non-empty rest.
+    fn parse_wildcard<'a>(&self, part: &'a str) -> Option<(&'a str, bool)> {
+        let inner = part.strip_prefix('{')?.strip_suffix('}')?;
+
+        if let Some(name) = inner.strip_prefix('*') {
+            return Some((name, false));
+        }
+
+        inner.strip_prefix('+').map(|name| (name, true))
+    }
+
+    // Returns everything in the URI path after the first `skip` segments.
+    // `None` means the path ends right after them (`/files`), while an empty
+    // string means it ends with a slash (`/files/`).
+    fn wildcard_rest<'a>(&self, uri_path: &'a str, skip: usize) -> Option<&'a str> {
+        let mut rest = uri_path.trim_start_matches('/');
+
+        for _ in 0..skip {
+            rest = match rest.find('/') {
+                Some(i) => rest[i..].trim_start_matches('/'),
+                None => return None,
+            };
+        }
+
+        if skip == 0 {
+            return Some(rest);
+        }
+
+        let consumed = uri_path.len() - rest.len();
+
+        if uri_path[..consumed].ends_with('/') {
+            Some(rest)
+        } else {
+            None
+        }
+    }
+
+    /// Produces the response to `request` just like the server would, for
+    /// running Bobby's routing behind another server or runtime, e.g. an edge
+    /// or serverless adapter.
+    pub async fn respond<B>(
         &self,
-        _req: &hyper::Request<hyper::body::Incoming>,
-    ) -> Result<hyper::Response<String>, ResponseError> {
-        // attempt to find a matching route
-        for route in &self.routes {
-            if _req.method() == route.method && self.uri_matches_path(_req.uri(), &route.path) {
-                let mut req = Request::new(_req);
+        request: hyper::Request<B>,
+    ) -> Result<hyper::Response<HttpBody>, ResponseError>
+    where
+        B: hyper::body::Body<Data = Bytes> + Send + Unpin + 'static,
+        B::Error: Into<BoxError>,
+    {
+        self.route(request).await
+    }
+
+    /// Produces the response to a request in-process, without a server or
+    /// socket, going through the same checks, hooks, matching and middleware
+    /// as served requests. The body is read in full, so it comes back as
+    /// bytes even if the handler streamed it. For unit tests: it blocks, so
+    /// call it from plain `#[test]` functions. A `uri` that doesn't parse
+    /// gets a `400`, and a response that can't be built a `500`.
+    ///
+    /// No runtime is started, so handlers that need Tokio's, as do static
+    /// files and `with_request_timeout`, need one entered around the call.
+    pub fn handle(
+        &self,
+        method: hyper::Method,
+        uri: &str,
+        headers: HeaderMap,
+        body: impl Into<Bytes>,
+    ) -> Response {
+        let Ok(mut request) = hyper::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Full::new(body.into()))
+        else {
+            warn!("Invalid request URI \"{}\"", uri);
+
+            return HttpError::status(400).into_response();
+        };
+
+        *request.headers_mut() = headers;
+
+        util::block_on(async {
+            let (parts, body) = match self.route(request).await {
+                Ok(response) => response.into_parts(),
+                Err(err) => {
+                    error!("Cannot build the response for {}: {}", uri, err);
+
+                    return HttpError::status(500).into_response();
+                }
+            };
+
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(err) => {
+                    error!("Cannot read the response body for {}: {}", uri, err);
+
+                    return HttpError::status(500).into_response();
+                }
+            };
+
+            let headers = parts
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+
+                    (name.to_string(), value)
+                })
+                .collect();
+
+            Response {
+                body: Body::Bytes(body),
+                status: parts.status.as_u16(),
+                headers,
+                ..Response::html("")
+            }
+        })
+    }
+
+    async fn route<B>(
+        &self,
+        mut _req: hyper::Request<B>,
+    ) -> Result<hyper::Response<HttpBody>, ResponseError>
+    where
+        B: hyper::body::Body<Data = Bytes> + Send + Unpin + 'static,
+        B::Error: Into<BoxError>,
+    {
+        let cancellation = self.shutdown.child_token();
+
+        _req.extensions_mut()
+            .insert(RequestCancellation(cancellation.clone()));
+
+        // the header is there before the body, so everything from here on,
+        // timeouts and streaming routes included, goes by the method it asks for
+        if self.method_override
+            && _req.method() == hyper::Method::POST
+            && let Some(method) = _req
+                .headers()
+                .get("x-http-method-override")
+                .and_then(|value| value.to_str().ok())
+                .and_then(overridden_method)
+        {
+            *_req.method_mut() = method;
+        }
+
+        let started = util::now();
+        let method = _req.method().clone();
+        let access_log = self
+            .access_log
+            .map(|format| (format, self.access_log_entry(&_req)));
+        let host = request_host(_req.headers(), _req.uri(), self.trust_proxy);
+        let resolved = self.resolve(_req.method(), _req.uri(), host);
+        let timeout = self.timeout_for(&resolved);
+        let target = timeout.map(|_| (_req.version(), _req.uri().clone()));
+
+        let mut response = match self.dispatch_within(_req, resolved, timeout).await {
+            Some(response) => response,
+            None => {
+                if let (Some(timeout), Some((version, uri))) = (timeout, target) {
+                    warn!(
+                        "{:?} {} {} - Timed out after {:?}",
+                        version, method, uri, timeout
+                    );
+                }
+
+                // lets whatever the handler left running know to stop
+                cancellation.cancel();
+
+                Response::html("Service unavailable.")
+                    .with_status(503)
+                    .build()
+            }
+        };
+
+        if let Ok(response) = &mut response {
+            match &self.server_header {
+                ServerHeader::Unset => {}
+                ServerHeader::Set(value) => {
+                    response
+                        .headers_mut()
+                        .entry(header::SERVER)
+                        .or_insert_with(|| value.clone());
+                }
+                ServerHeader::Suppressed => {
+                    response.headers_mut().remove(header::SERVER);
+                }
+            }
+        }
+
+        if let Some((format, mut entry)) = access_log {
+            if let Ok(response) = &response {
+                entry.status = response.status().as_u16();
+                entry.matched_route = response
+                    .extensions()
+                    .get::<MatchedPath>()
+                    .map(|matched| matched.0.clone());
+                // HEAD keeps GET's `Content-Length` but sends nothing
+                entry.bytes = if method == hyper::Method::HEAD {
+                    Some(0)
+                } else {
+                    response
+                        .headers()
+                        .get(header::CONTENT_LENGTH)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse().ok())
+                };
+            }
+
+            entry.duration = started.map(|started| started.elapsed()).unwrap_or_default();
+            entry.write(format);
+        }
+
+        if let Some(on_request_timing) = &self.on_request_timing {
+            let status = response.as_ref().map_or(500, |r| r.status().as_u16());
+            let matched_path = response
+                .as_ref()
+                .ok()
+                .and_then(|r| r.extensions().get::<MatchedPath>())
+                .map(|matched| matched.0.as_str());
+
+            let elapsed = started.map(|started| started.elapsed()).unwrap_or_default();
+
+            on_request_timing(matched_path, &method, status, elapsed);
+        }
+
+        response.map(|response| {
+            // HEAD gets the headers GET would, `Content-Length` included,
+            // but never the body
+            let response = if method == hyper::Method::HEAD {
+                response.map(|_| Body::empty().into_http_body())
+            } else {
+                response
+            };
+
+            response.map(|body| CancelOnDrop::new(body, cancellation).boxed_unsync())
+        })
+    }
+
+    // Finds the route for `method` and `uri`, with HEAD answered by the GET
+    // route unless it has one of its own.
+    fn resolve(
+        &self,
+        method: &hyper::Method,
+        uri: &hyper::Uri,
+        host: Option<&str>,
+    ) -> Resolved<'_> {
+        let route = self
+            .find_route(method, uri, host)
+            .or_else(|| match *method {
+                hyper::Method::HEAD => self.find_route(&hyper::Method::GET, uri, host),
+                _ => None,
+            });
+
+        Resolved {
+            method: method.clone(),
+            uri: uri.clone(),
+            route,
+        }
+    }
+
+    // The timeout of the route the request is for, or else the app's.
+    fn timeout_for(&self, resolved: &Resolved) -> Option<Duration> {
+        resolved
+            .route
+            .as_ref()
+            .and_then(|(route, _)| route.timeout)
+            .or(self.request_timeout)
+    }
+
+    // Dispatches the request, giving up with `None` once it's taken longer
+    // than `timeout`. Reading the body and static files stops right there,
+    // but handlers run synchronously and can't be interrupted, so one that
+    // overruns is left to finish and its response dropped.
+    async fn dispatch_within<B>(
+        &self,
+        request: hyper::Request<B>,
+        resolved: Resolved<'_>,
+        timeout: Option<Duration>,
+    ) -> Option<Result<hyper::Response<HttpBody>, ResponseError>>
+    where
+        B: hyper::body::Body<Data = Bytes> + Send + Unpin + 'static,
+        B::Error: Into<BoxError>,
+    {
+        // there's neither a timer nor a clock without the server's runtime,
+        // so timeouts go unenforced there
+        #[cfg(feature = "server")]
+        if let Some(timeout) = timeout {
+            let started = Instant::now();
+            let response = tokio::time::timeout(timeout, self.dispatch(request, resolved))
+                .await
+                .ok()?;
+
+            return (started.elapsed() < timeout).then_some(response);
+        }
+        #[cfg(not(feature = "server"))]
+        let _ = timeout;
+
+        Some(self.dispatch(request, resolved).await)
+    }
+
+    // Captures what the access log needs from the request before it's
+    // consumed, leaving the response fields to be filled in afterwards.
+    fn access_log_entry<B>(&self, request: &hyper::Request<B>) -> AccessLogEntry {
+        let header = |name: header::HeaderName| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+
+        let peer = request.extensions().get::<PeerAddr>().map(|peer| peer.0);
+
+        AccessLogEntry {
+            method: request.method().to_string(),
+            target: request.uri().to_string(),
+            path: String::from(request.uri().path()),
+            version: format!("{:?}", request.version()),
+            matched_route: None,
+            status: 500,
+            duration: Duration::ZERO,
+            client_ip: client_ip(request.headers(), peer, self.trust_proxy),
+            bytes: None,
+            referer: header(header::REFERER),
+            user_agent: header(header::USER_AGENT),
+        }
+    }
+
+    async fn dispatch<B>(
+        &self,
+        _req: hyper::Request<B>,
+        resolved: Resolved<'_>,
+    ) -> Result<hyper::Response<HttpBody>, ResponseError>
+    where
+        B: hyper::body::Body<Data = Bytes> + Send + Unpin + 'static,
+        B::Error: Into<BoxError>,
+    {
+        // the only expectation there is, is `100-continue`
+        if let Some(expect) = _req.headers().get(header::EXPECT)
+            && !expect.as_bytes().eq_ignore_ascii_case(b"100-continue")
+        {
+            self.log_request(&_req, log::Level::Warn, "Expectation failed");
+
+            return Response::html("Expectation failed.")
+                .with_status(417)
+                .with_header("Connection", "close")
+                .build();
+        }
+
+        // reject declared bodies over the limit before reading any of it.
+        // hyper only sends `100 Continue` once the body is read, so clients
+        // waiting on it get the 413 straight away and skip the upload
+        if self.exceeds_max_body_size(&_req) {
+            self.log_request(&_req, log::Level::Warn, "Payload too large");
+
+            return Response::html("Payload too large.")
+                .with_status(413)
+                .with_header("Connection", "close")
+                .build();
+        }
+
+        // in maintenance mode only allow-listed paths get through
+        if self.maintenance.load(Ordering::Relaxed)
+            && !self
+                .maintenance_allow
+                .iter()
+                .any(|path| self.uri_matches_path(_req.uri(), path))
+        {
+            self.log_request(&_req, log::Level::Warn, "Maintenance");
+
+            return Response::html("Down for maintenance.")
+                .with_status(503)
+                .with_retry_after(self.maintenance_retry_after)
+                .build();
+        }
+
+        // with the strict query policy, malformed query strings are rejected
+        if self.strict_query
+            && let Some(query) = _req.uri().query()
+            && !is_well_formed(query)
+        {
+            self.log_request(&_req, log::Level::Warn, "Malformed query string");
+
+            return Response::html("Bad request.").with_status(400).build();
+        }
+
+        // hand CONNECT tunnels to the hook
+        #[cfg(feature = "server")]
+        if _req.method() == hyper::Method::CONNECT
+            && let Some(on_connect) = &self.on_connect
+        {
+            return self.connect(_req, Arc::clone(on_connect));
+        }
+
+        // streaming routes get the body as it arrives, still counting bytes
+        // against the limit
+        if resolved
+            .route
+            .as_ref()
+            .is_some_and(|(route, _)| route.stream_body)
+        {
+            let (parts, incoming) = _req.into_parts();
+            let _req = hyper::Request::from_parts(parts, ());
+
+            let body_stream = Limited::new(incoming, self.max_body_size).into_data_stream();
+
+            return self
+                .route_request(&_req, Bytes::new(), Some(Box::pin(body_stream)), resolved)
+                .await;
+        }
+
+        // buffer the body, counting bytes as they come in for bodies that
+        // didn't declare their length up front
+        let (parts, incoming) = _req.into_parts();
+        let mut _req = hyper::Request::from_parts(parts, ());
+
+        let body = match Limited::new(incoming, self.max_body_size).collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(err) if err.downcast_ref::<LengthLimitError>().is_some() => {
+                self.log_request(&_req, log::Level::Warn, "Payload too large");
+
+                return Response::html("Payload too large.")
+                    .with_status(413)
+                    .with_header("Connection", "close")
+                    .build();
+            }
+            Err(err) => {
+                self.log_request(&_req, log::Level::Warn, format!("Bad request: {}", err));
+
+                return Response::html("Bad request.").with_status(400).build();
+            }
+        };
+
+        if self.method_override
+            && _req.method() == hyper::Method::POST
+            && is_form(_req.headers())
+            && let Some(method) = std::str::from_utf8(&body)
+                .ok()
+                .and_then(|form| parse_query(form).remove("_method"))
+                .and_then(|value| overridden_method(&value))
+        {
+            *_req.method_mut() = method;
+        }
+
+        self.route_request(&_req, body, None, resolved).await
+    }
+
+    // Matches the request, its body either buffered or left as a stream,
+    // against the routes, the OpenAPI spec and static files.
+    async fn route_request(
+        &self,
+        _req: &hyper::Request<()>,
+        body: Bytes,
+        body_stream: Option<BodyStream>,
+        resolved: Resolved<'_>,
+    ) -> Result<hyper::Response<HttpBody>, ResponseError> {
+        let mut req = Request::new(_req);
+
+        req.body = body;
+        req.body_stream = body_stream;
+        #[cfg(feature = "json")]
+        {
+            req.json_limits = self.json_limits;
+        }
+        req.trust_proxy = self.trust_proxy;
+        req.state = Arc::clone(&self.state);
+        req.cancellation = _req
+            .extensions()
+            .get::<RequestCancellation>()
+            .map_or_else(|| self.shutdown.clone(), |token| token.0.clone());
+
+        // let hooks rewrite the request and attach context before matching
+        for hook in &self.before_route {
+            hook(&mut req);
+        }
 
-                if let Some(params) = self.extract_params(_req.uri(), &route.path) {
-                    req.params = params;
+        // attempt to find a matching route, unless it's a preflight the `cors`
+        // middleware answers before anything routed gets a say
+        let host = request_host(&req.headers, &req.uri, req.trust_proxy);
+        let matched = if self.cors && cors::is_preflight(&req) {
+            Some((Endpoint::NotFound, None))
+        } else {
+            let route = if req.method == resolved.method && req.uri == resolved.uri {
+                resolved.route
+            } else {
+                self.resolve(&req.method, &req.uri, host).route
+            };
+
+            self.endpoint(&req, host, route)
+        };
+
+        // fall back to static files
+        #[cfg(feature = "fs")]
+        let matched = match matched {
+            None if req.method == hyper::Method::GET || req.method == hyper::Method::HEAD => {
+                self.static_file(&req.uri, _req.headers()).await
+            }
+            matched => matched,
+        };
+
+        let (mut endpoint, matched_path) =
+            matched.unwrap_or_else(|| (self.unmatched(_req, &req.uri, host), None));
+        let not_found = matches!(endpoint, Endpoint::NotFound);
+
+        if let Endpoint::Route(_, params) = &mut endpoint {
+            req.params = std::mem::take(params);
+        }
+
+        // middleware wraps whatever answers, the 404 and 405 included
+        let endpoint = move |req: Request| self.answer(endpoint, req);
+
+        let next = Next {
+            middleware: &self.middleware,
+            endpoint: Box::new(endpoint),
+        };
+
+        let response = next.run(req);
+        #[cfg(feature = "fs")]
+        let response = response.open_file().await;
+        let mut response = response.build()?;
+
+        if let Some(matched_path) = matched_path {
+            response.extensions_mut().insert(MatchedPath(matched_path));
+        }
+
+        if let Some(reason) = &self.not_found_status_text
+            && not_found
+            && response.status() == 404
+        {
+            response.extensions_mut().insert(reason.clone());
+        }
+
+        Ok(response)
+    }
+
+    // Produces the response of what was matched.
+    fn answer(&self, endpoint: Endpoint, req: Request) -> Response {
+        match endpoint {
+            Endpoint::Route(route, _) => match route.cache_ttl {
+                Some(ttl) if req.method == hyper::Method::GET => self.cached(route, req, ttl),
+                _ => self.call(route, req),
+            },
+            Endpoint::Ready(response) => response,
+            Endpoint::MethodNotAllowed(allow) => {
+                let response = match &self.method_not_allowed {
+                    Some(handler) => self.apply_layout(handler(req)),
+                    None => Response::html("Method not allowed.").with_status(405),
+                };
+
+                if response
+                    .headers
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case("allow"))
+                {
+                    response
+                } else {
+                    response.with_header("Allow", allow)
                 }
+            }
+            Endpoint::NotFound => match &self.not_found {
+                Some(handler) => self.apply_layout(handler(req)),
+                None => Response::html(self.not_found_body.clone()).with_status(404),
+            },
+        }
+    }
+
+    // Redirects to the request path with its trailing slash added or removed
+    // if a route matches that instead.
+    fn trailing_slash_redirect(&self, req: &Request, host: Option<&str>) -> Option<Response> {
+        let path = req.uri.path();
+
+        if path == "/" {
+            return None;
+        }
+
+        // built from the segments alone, so `//evil.com/` can't redirect
+        // off-site
+        let trimmed = collapse_slashes(path);
+        let other = if path.ends_with('/') {
+            trimmed
+        } else {
+            format!("{}/", trimmed)
+        };
+        let uri: hyper::Uri = other.parse().ok()?;
+        let method = match req.method {
+            hyper::Method::HEAD => &hyper::Method::GET,
+            ref method => method,
+        };
+
+        self.find_route(&req.method, &uri, host)
+            .or_else(|| self.find_route(method, &uri, host))?;
+
+        let location = with_query(other, req.uri.query());
+
+        let status = match req.method {
+            hyper::Method::GET | hyper::Method::HEAD => 301,
+            _ => 308,
+        };
+
+        Some(Response::redirect(location).with_status(status))
+    }
+
+    // Looks for what answers the request among its resolved route, the
+    // OpenAPI spec and the echo endpoint, along with the pattern that matched.
+    fn endpoint<'a>(
+        &'a self,
+        req: &Request,
+        host: Option<&str>,
+        route: Option<(&'a Route, HashMap<String, String>)>,
+    ) -> Option<(Endpoint<'a>, Option<String>)> {
+        if let Some((route, params)) = route {
+            return Some((Endpoint::Route(route, params), Some(route.path.clone())));
+        }
+
+        // the other form of a route's path, with or without the slash
+        if self.trailing_slash == TrailingSlash::Redirect
+            && let Some(response) = self.trailing_slash_redirect(req, host)
+        {
+            return Some((Endpoint::Ready(response), None));
+        }
+
+        // the generated spec, if enabled
+        #[cfg(feature = "json")]
+        if let Some(openapi) = &self.openapi
+            && (req.method == hyper::Method::GET || req.method == hyper::Method::HEAD)
+            && req.uri.path() == openapi.path
+        {
+            let spec = self.openapi_spec(&openapi.title, &openapi.version);
+            let response = Response::bytes(spec.to_string(), "application/json");
+
+            return Some((Endpoint::Ready(response), Some(openapi.path.clone())));
+        }
+
+        // the echo endpoint, if enabled
+        #[cfg(feature = "json")]
+        if let Some(echo_path) = &self.echo_path
+            && req.uri.path() == echo_path
+        {
+            let echo = format!("{:#}", req.echo());
+            let response = Response::bytes(echo, "application/json");
+
+            return Some((Endpoint::Ready(response), Some(echo_path.clone())));
+        }
 
-                let response = (route.callable)(req);
+        None
+    }
 
-                return response.build();
+    // Serves the file `uri` points to from the first static files mount that
+    // has it.
+    #[cfg(feature = "fs")]
+    async fn static_file(
+        &self,
+        uri: &hyper::Uri,
+        headers: &HeaderMap,
+    ) -> Option<(Endpoint<'_>, Option<String>)> {
+        for static_files in &self.static_files {
+            if let Some(response) = static_files.serve(uri, headers).await {
+                return Some((Endpoint::Ready(response), Some(static_files.pattern())));
             }
         }
 
-        // no matching route found
+        None
+    }
+
+    // A 405 when the path is routed, just not for this method, or else a 404.
+    fn unmatched(
+        &self,
+        _req: &hyper::Request<()>,
+        uri: &hyper::Uri,
+        host: Option<&str>,
+    ) -> Endpoint<'_> {
+        let allowed = self.allowed_methods(uri, host);
+
+        if !allowed.is_empty() {
+            self.log_request(_req, log::Level::Warn, "Method not allowed");
+
+            let allow: Vec<&str> = allowed.iter().map(|method| method.as_str()).collect();
+
+            return Endpoint::MethodNotAllowed(allow.join(", "));
+        }
+
         self.log_request(_req, log::Level::Warn, "Not found");
 
-        Response::html("Not found.").with_status(404).build()
+        Endpoint::NotFound
+    }
+
+    // Calls the route's handler, wrapping full HTML pages in the layout.
+    fn call(&self, route: &Route, req: Request) -> Response {
+        if let Some((target, status)) = &route.redirect {
+            return Response::redirect(redirect_location(target, &req)).with_status(*status);
+        }
+
+        self.apply_layout((route.callable)(req))
+    }
+
+    // Wraps full HTML pages in the layout, if there is one.
+    fn apply_layout(&self, mut response: Response) -> Response {
+        if let Some(layout) = &self.layout
+            && !response.fragment
+            && response.headers.iter().any(|(name, value)| {
+                name.eq_ignore_ascii_case("content-type") && value.starts_with("text/html")
+            })
+            && let Body::Text(content) = response.body
+        {
+            response.body = Body::Text(layout(content));
+        }
+
+        response
+    }
+
+    // Answers from the response cache when possible, otherwise calls the
+    // handler and caches successful, fully buffered responses for `ttl`.
+    fn cached(&self, route: &Route, req: Request, ttl: Duration) -> Response {
+        let mut key = req.uri.path_and_query().map_or_else(
+            || String::from(req.uri.path()),
+            |pq| String::from(pq.as_str()),
+        );
+
+        // host-scoped routes answer differently per host
+        if route.host.is_some() {
+            let host = request_host(&req.headers, &req.uri, req.trust_proxy).unwrap_or_default();
+
+            key = format!("{}{}", host.to_ascii_lowercase(), key);
+        }
+
+        // credentials mean the response is for whoever sent them
+        if req.headers.contains_key(header::AUTHORIZATION) {
+            return self.call(route, req);
+        }
+
+        // entries couldn't expire without a clock, so there's no caching
+        let Some(now) = util::now() else {
+            return self.call(route, req);
+        };
+
+        let headers = req.headers.clone();
+
+        if let Some(cached) = self.response_cache.lock().unwrap().get(&key, &headers, now) {
+            return Response::from(cached);
+        }
+
+        let response = self.call(route, req);
+
+        // cookies are meant for whoever the response was made for, whether
+        // they're set with `with_cookie` or as a header
+        let sets_cookies = !response.cookies.is_empty()
+            || response
+                .headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case("Set-Cookie"));
+
+        // and the response can opt out with `Cache-Control` itself
+        let uncacheable = response.headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("Cache-Control")
+                && value.split(',').any(|directive| {
+                    let directive = directive.trim();
+
+                    directive.eq_ignore_ascii_case("no-store")
+                        || directive.eq_ignore_ascii_case("private")
+                })
+        });
+
+        if !(200..300).contains(&response.status) || sets_cookies || uncacheable {
+            return response;
+        }
+
+        // files are opened after this, and streamed rather than buffered
+        #[cfg(feature = "fs")]
+        if response.file.is_some() {
+            return response;
+        }
+
+        let body = match &response.body {
+            Body::Text(text) => Bytes::from(text.clone()),
+            Body::Bytes(bytes) => bytes.clone(),
+            Body::Stream(_) => return response,
+        };
+
+        let cached = CachedResponse {
+            status: response.status,
+            headers: response.headers.clone(),
+            body,
+        };
+
+        self.response_cache
+            .lock()
+            .unwrap()
+            .insert(key, cached, &headers, ttl, now);
+
+        response
+    }
+
+    fn exceeds_max_body_size<B>(&self, request: &hyper::Request<B>) -> bool {
+        request
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .is_some_and(|len| len > self.max_body_size as u64)
     }
 
     fn extract_params(&self, uri: &hyper::Uri, path: &str) -> Option<HashMap<String, String>> {
@@ -339,12 +3033,16 @@ impl Bobby {
         let mut params = HashMap::new();
 
         for (i, path_part) in path_parts.iter().enumerate() {
+            if let Some((param_name, _)) = self.parse_wildcard(path_part) {
+                if let Some(rest) = self.wildcard_rest(uri.path(), i) {
+                    params.insert(String::from(param_name), String::from(rest));
+                }
+
+                break;
+            }
+
             if path_part.starts_with('{') && path_part.ends_with('}') {
-                let param_name = if path_part.ends_with("?}") {
-                    &path_part[1..path_part.len() - 2]
-                } else {
-                    &path_part[1..path_part.len() - 1]
-                };
+                let (param_name, _, _) = pattern::param_parts(&path_part[1..path_part.len() - 1]);
 
                 if i < uri_parts.len() {
                     params.insert(String::from(param_name), String::from(uri_parts[i]));
@@ -355,49 +3053,171 @@ impl Bobby {
         Some(params)
     }
 
-    async fn listen(&self) {
-        let addr = SocketAddr::from((self.ip, self.port));
+    // Finds the most specific enabled route matching the method, path and
+    // host, along with its params, going by registration order between
+    // equally specific ones. Routes without params are looked up by path
+    // rather than matched segment by segment, and as nothing is more specific
+    // the first of those to match is the one.
+    fn find_route(
+        &self,
+        method: &hyper::Method,
+        uri: &hyper::Uri,
+        host: Option<&str>,
+    ) -> Option<(&Route, HashMap<String, String>)> {
+        let usable = |route: &Route| {
+            route.method == method
+                && route.enabled.load(Ordering::Relaxed)
+                && self.trailing_slash_matches(uri.path(), &route.path)
+        };
+
+        // a static route is more specific than any parametric one, so the
+        // first that matches wins without segment matching the rest
+        for &i in self.route_index.exact(uri.path()) {
+            let route = &self.routes[i];
+
+            if usable(route)
+                && let Some(params) = self.host_params(route, host)
+            {
+                return Some((route, params));
+            }
+        }
 
-        if let Ok(listener) = TcpListener::bind(addr).await {
-            let bobby_arc = Arc::new(self.clone());
+        let mut best: Option<(&Route, HashMap<String, String>)> = None;
 
-            loop {
-                if let Ok((stream, _)) = listener.accept().await {
-                    let io = TokioIo::new(stream);
-                    let bobby = Arc::clone(&bobby_arc);
+        for &i in self.route_index.parametric() {
+            let route = &self.routes[i];
 
-                    tokio::task::spawn(async move {
-                        let service = service_fn(move |request| {
-                            let bobby_ref = Arc::clone(&bobby);
+            if !usable(route)
+                || best.as_ref().is_some_and(|(best, _)| {
+                    !pattern::more_specific(&route.specificity, &best.specificity)
+                })
+                || !self.uri_matches_path(uri, &route.path)
+            {
+                continue;
+            }
 
-                            async move {
-                                bobby_ref.log_request(&request, log::Level::Info, "");
-                                bobby_ref.route(&request)
-                            }
-                        });
+            let Some(mut params) = self.host_params(route, host) else {
+                continue;
+            };
 
-                        if let Err(err) = auto::Builder::new(TokioExecutor::new())
-                            .serve_connection(io, service)
-                            .await
-                        {
-                            error!("Error: {}", err);
-                        }
-                    });
-                } else {
-                    error!("Could not start a listener.");
+            if let Some(path_params) = self.extract_params(uri, &route.path) {
+                if !constraints_hold(route, &path_params) {
+                    continue;
                 }
+
+                params.extend(path_params);
             }
-        } else {
-            error!("Could not bind to configured address and port.");
+
+            best = Some((route, params));
         }
+
+        best
     }
 
-    pub fn run(&self) {
-        if let Ok(rt) = tokio::runtime::Runtime::new() {
-            info!("Listening on {}:{} ...", self.ip, self.port);
-            rt.block_on(self.listen());
-        } else {
-            error!("Could not start runtime.");
+    // The methods of the enabled routes matching `uri` and `host`, in the
+    // order they were registered, plus HEAD wherever GET is.
+    fn allowed_methods(&self, uri: &hyper::Uri, host: Option<&str>) -> Vec<&hyper::Method> {
+        let mut methods: Vec<&hyper::Method> = vec![];
+
+        for (i, parametric) in self.route_index.candidates(uri.path()) {
+            let route = &self.routes[i];
+
+            if methods.contains(&&route.method)
+                || !route.enabled.load(Ordering::Relaxed)
+                || !self.trailing_slash_matches(uri.path(), &route.path)
+                || (parametric && !self.uri_matches_path(uri, &route.path))
+                || (!route.constraints.is_empty()
+                    && !self
+                        .extract_params(uri, &route.path)
+                        .is_some_and(|params| constraints_hold(route, &params)))
+                || self.host_params(route, host).is_none()
+            {
+                continue;
+            }
+
+            methods.push(&route.method);
+        }
+
+        if methods.contains(&&hyper::Method::GET) && !methods.contains(&&hyper::Method::HEAD) {
+            methods.push(&hyper::Method::HEAD);
+        }
+
+        methods
+    }
+
+    // Matches the request host against the route's host pattern, if it has
+    // one, returning the captured params. Labels compare case-insensitively
+    // and any port is ignored.
+    fn host_params(&self, route: &Route, host: Option<&str>) -> Option<HashMap<String, String>> {
+        let Some(pattern) = &route.host else {
+            return Some(HashMap::new());
+        };
+
+        let host = host?;
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if port.bytes().all(|b| b.is_ascii_digit()) => name,
+            _ => host,
+        };
+
+        let pattern_labels: Vec<&str> = pattern.split('.').collect();
+        let host_labels: Vec<&str> = host.trim_end_matches('.').split('.').collect();
+
+        if pattern_labels.len() != host_labels.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+
+        for (pattern_label, host_label) in pattern_labels.iter().zip(host_labels) {
+            match pattern_label
+                .strip_prefix('{')
+                .and_then(|l| l.strip_suffix('}'))
+            {
+                Some(name) if !host_label.is_empty() => {
+                    params.insert(String::from(name), host_label.to_ascii_lowercase());
+                }
+                None if pattern_label.eq_ignore_ascii_case(host_label) => {}
+                _ => return None,
+            }
+        }
+
+        Some(params)
+    }
+
+    /// Turns maintenance mode on or off. While on, every request except those
+    /// matching `allow_during_maintenance` paths gets a 503. Like `shutdown`,
+    /// this can be called on a clone of the app while it's running.
+    pub fn set_maintenance(&self, enabled: bool) {
+        self.maintenance.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Keeps a path reachable during maintenance, e.g. a health check. Takes
+    /// a route pattern, so `/admin/{*rest}` allows everything under `/admin`,
+    /// and panics on a malformed one, as `get` and friends do. Inside a
+    /// `group`, the group's prefix goes in front.
+    pub fn allow_during_maintenance(&mut self, path: impl Into<String>) -> &mut Self {
+        let path = pattern::normalize(&self.prefixed(path.into()));
+
+        if let Err(err) = pattern::validate(&path) {
+            panic!("Cannot allow {} during maintenance: {}", path, err);
         }
+
+        self.maintenance_allow.push(path);
+        self
+    }
+
+    /// Sets the `Retry-After` sent with maintenance responses. Defaults to
+    /// 120 seconds.
+    pub fn with_maintenance_retry_after(&mut self, retry_after: Duration) -> &mut Self {
+        self.maintenance_retry_after = retry_after;
+        self
+    }
+
+    /// Starts a graceful shutdown: stops accepting connections, cancels
+    /// `Request::cancellation` tokens, and lets `run` return once in-flight
+    /// requests have finished. Ctrl-C does the same. Clones of the app share
+    /// the signal, so call this on a clone taken before `run`.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
     }
 }
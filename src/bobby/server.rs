@@ -0,0 +1,432 @@
+use super::*;
+use crate::idle::{Activity, IdleIo};
+use hyper::{service::service_fn, upgrade::Upgraded};
+use hyper_util::{
+    rt::{TokioIo, TokioTimer},
+    server::conn::auto::{self},
+};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{pin::Pin, sync::atomic::AtomicU64};
+use tokio::net::TcpListener;
+use tokio_util::task::TaskTracker;
+
+#[cfg(feature = "tls")]
+use crate::tls::TlsError;
+
+#[derive(Clone)]
+struct TokioExecutor;
+
+impl<F> hyper::rt::Executor<F> for TokioExecutor
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        tokio::task::spawn(fut);
+    }
+}
+
+impl TokioExecutor {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+// Whether a connection error is just the client going away, e.g. closing
+// the tab mid-download, rather than something worth an error log.
+fn is_disconnect(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<hyper::Error>()
+            && (err.is_incomplete_message() || err.is_canceled() || err.is_closed())
+        {
+            return true;
+        }
+
+        if let Some(err) = err.downcast_ref::<std::io::Error>()
+            && matches!(
+                err.kind(),
+                std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::UnexpectedEof
+            )
+        {
+            return true;
+        }
+
+        source = err.source();
+    }
+
+    false
+}
+
+// The first wait after a failed accept, doubled with every failure in a row.
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+
+// Whether a failed accept means the listener itself is broken (no longer
+// listening, or a bad descriptor), so retrying can never succeed. Everything
+// else, like running out of file descriptors or memory, is assumed to pass.
+fn is_fatal_accept_error(err: &std::io::Error) -> bool {
+    // EBADF, which is 9 on every unix
+    #[cfg(unix)]
+    if err.raw_os_error() == Some(9) {
+        return true;
+    }
+
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput | std::io::ErrorKind::Unsupported
+    )
+}
+
+// Adds up to half of `delay` on top, so that several servers hitting the same
+// limit don't all retry in lockstep.
+fn with_jitter(delay: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos());
+
+    delay + delay.mul_f64(f64::from(nanos % 1000) / 2000.0)
+}
+
+/// A client connection upgraded by `CONNECT`, readable and writable with
+/// tokio's `AsyncRead` and `AsyncWrite`.
+pub type Tunnel = TokioIo<Upgraded>;
+
+pub type ConnectHook =
+    Arc<dyn Fn(String, Tunnel) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+// A client connection, plain or behind TLS.
+#[cfg(feature = "tls")]
+trait Connection: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send {}
+
+#[cfg(feature = "tls")]
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send> Connection for T {}
+
+impl Bobby {
+    /// Serves HTTPS with the PEM certificate chain at `cert_path` and the PEM
+    /// private key at `key_path`, which are loaded right away so that a bad
+    /// file fails here rather than on the first connection. HTTP/2 and
+    /// HTTP/1.1 are both offered to clients.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(
+        &mut self,
+        cert_path: impl AsRef<std::path::Path>,
+        key_path: impl AsRef<std::path::Path>,
+    ) -> Result<&mut Self, TlsError> {
+        let read = |path: &std::path::Path| {
+            std::fs::read(path).map_err(|err| TlsError::Read(path.display().to_string(), err))
+        };
+
+        let cert = read(cert_path.as_ref())?;
+        let key = read(key_path.as_ref())?;
+
+        self.with_tls_pem(&cert, &key)
+    }
+
+    /// Like `with_tls`, but with the PEM certificate chain and private key
+    /// given as bytes, e.g. from a secrets store.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_pem(&mut self, cert: &[u8], key: &[u8]) -> Result<&mut Self, TlsError> {
+        self.tls = Some(crate::tls::acceptor(cert, key)?);
+
+        Ok(self)
+    }
+
+    /// Handles `CONNECT` requests by accepting the tunnel and calling `hook`
+    /// with the requested authority (`host:port`) and the upgraded client
+    /// connection, which it can then pipe to wherever it likes. Without a
+    /// hook `CONNECT` requests are routed like any other.
+    pub fn on_connect(
+        &mut self,
+        hook: impl Fn(String, Tunnel) -> Pin<Box<dyn Future<Output = ()> + Send>>
+        + Send
+        + Sync
+        + 'static,
+    ) -> &mut Self {
+        self.on_connect = Some(Arc::new(hook));
+        self
+    }
+
+    // Accepts the tunnel with a bodiless 200 and runs the hook on the upgraded
+    // connection once hyper has handed it over.
+    pub(super) fn connect<B>(
+        &self,
+        mut _req: hyper::Request<B>,
+        on_connect: ConnectHook,
+    ) -> Result<hyper::Response<HttpBody>, ResponseError> {
+        let Some(authority) = _req.uri().authority().map(|a| a.to_string()) else {
+            self.log_request(&_req, log::Level::Warn, "CONNECT without authority");
+
+            return Response::html("Bad request.").with_status(400).build();
+        };
+
+        let on_upgrade = hyper::upgrade::on(&mut _req);
+
+        tokio::task::spawn(async move {
+            match on_upgrade.await {
+                Ok(upgraded) => on_connect(authority, TokioIo::new(upgraded)).await,
+                Err(err) => debug!("CONNECT upgrade failed: {}", err),
+            }
+        });
+
+        // a 2xx to CONNECT must not carry Content-Length or a body
+        hyper::Response::builder()
+            .status(200)
+            .body(Body::empty().into_http_body())
+            .map_err(|_| ResponseError::FailedToCreateHeader)
+    }
+
+    // Whether anything besides the stock 404 page could answer a request.
+    fn answers_anything(&self) -> bool {
+        #[cfg(feature = "json")]
+        let spec_or_echo = self.openapi.is_some() || self.echo_path.is_some();
+        #[cfg(not(feature = "json"))]
+        let spec_or_echo = false;
+
+        spec_or_echo
+            || !self.routes.is_empty()
+            || !self.static_files.is_empty()
+            || !self.middleware.is_empty()
+            || self.not_found.is_some()
+            || self.on_connect.is_some()
+    }
+
+    async fn listen(&self) {
+        let addr = SocketAddr::from((self.ip, self.port));
+
+        let Ok(listener) = self.bind_listener(addr) else {
+            error!("Could not bind to configured address and port.");
+            return;
+        };
+
+        // ctrl-c starts the same graceful shutdown as `Bobby::shutdown`
+        let shutdown = self.shutdown.clone();
+
+        tokio::task::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                shutdown.cancel();
+            }
+        });
+
+        self.serve(listener).await;
+    }
+
+    // Accepts connections on `listener` until shutdown, then waits for the
+    // open ones to finish.
+    pub(super) async fn serve(&self, listener: TcpListener) {
+        let bobby_arc = Arc::new(self.clone());
+        let connections = TaskTracker::new();
+        let mut backoff = None;
+
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = self.shutdown.cancelled() => break,
+            };
+
+            let (stream, peer) = match accepted {
+                Ok(accepted) => {
+                    backoff = None;
+                    accepted
+                }
+                // the client hung up before being accepted, nothing to wait for
+                Err(err) if is_disconnect(&err) => {
+                    debug!("Client disconnected before being accepted: {}", err);
+                    continue;
+                }
+                Err(err) if is_fatal_accept_error(&err) => {
+                    error!("Could not accept connections, stopping: {}", err);
+                    self.shutdown.cancel();
+                    break;
+                }
+                Err(err) => {
+                    let delay = backoff
+                        .map_or(MIN_ACCEPT_BACKOFF, |delay: Duration| delay * 2)
+                        .min(self.max_accept_backoff);
+
+                    backoff = Some(delay);
+
+                    let delay = with_jitter(delay);
+
+                    warn!(
+                        "Could not accept a connection, retrying in {:?}: {}",
+                        delay, err
+                    );
+
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => continue,
+                        _ = self.shutdown.cancelled() => break,
+                    }
+                }
+            };
+
+            let activity = Activity::new();
+            let requests = Arc::new(AtomicU64::new(0));
+            let opened = Instant::now();
+            let io = IdleIo::new(stream, activity.clone());
+            let bobby = Arc::clone(&bobby_arc);
+            let idle_timeout = self.idle_timeout;
+            let http2_max_concurrent_streams = self.http2_max_concurrent_streams;
+            let http2_max_pending_reset_streams = self.http2_max_pending_reset_streams;
+            let shutdown = self.shutdown.clone();
+
+            connections.spawn(async move {
+                // the handshake runs here rather than in the accept loop, so
+                // a slow client only holds up its own connection
+                #[cfg(feature = "tls")]
+                let io: Box<dyn Connection> = match &bobby.tls {
+                    Some(acceptor) => {
+                        match tokio::time::timeout(idle_timeout, acceptor.accept(io)).await {
+                            Ok(Ok(stream)) => Box::new(stream),
+                            Ok(Err(err)) => {
+                                debug!("TLS handshake with {} failed: {}", peer, err);
+                                return;
+                            }
+                            Err(_) => {
+                                debug!("TLS handshake with {} timed out", peer);
+                                return;
+                            }
+                        }
+                    }
+                    None => Box::new(io),
+                };
+
+                let io = TokioIo::new(io);
+                let on_connection_close = bobby.on_connection_close.clone();
+                let counter = Arc::clone(&requests);
+
+                let service = service_fn(move |mut request: hyper::Request<_>| {
+                    let bobby_ref = Arc::clone(&bobby);
+
+                    request.extensions_mut().insert(PeerAddr(peer));
+
+                    counter.fetch_add(1, Ordering::Relaxed);
+
+                    async move {
+                        if bobby_ref.request_log {
+                            bobby_ref.log_request(&request, log::Level::Info, "");
+                        }
+
+                        bobby_ref.route(request).await
+                    }
+                });
+
+                let mut builder = auto::Builder::new(TokioExecutor::new());
+
+                builder
+                    .http1()
+                    .timer(TokioTimer::new())
+                    .header_read_timeout(idle_timeout);
+
+                // only when set, as passing `None` to hyper lifts its limit
+                if let Some(max) = http2_max_concurrent_streams {
+                    builder.http2().max_concurrent_streams(max);
+                }
+
+                if let Some(max) = http2_max_pending_reset_streams {
+                    builder.http2().max_pending_accept_reset_streams(max);
+                }
+
+                let connection = builder.serve_connection_with_upgrades(io, service);
+                tokio::pin!(connection);
+
+                // race the connection against the idle guard and the
+                // shutdown signal, and when either fires let in-flight
+                // requests finish before closing
+                let mut shutting_down = false;
+
+                loop {
+                    let idle_for = activity.idle_for();
+
+                    tokio::select! {
+                        result = connection.as_mut() => {
+                            match result {
+                                Err(err) if is_disconnect(err.as_ref()) => {
+                                    debug!("Client disconnected: {}", err);
+                                }
+                                Err(err) => error!("Error: {}", err),
+                                Ok(()) => {}
+                            }
+
+                            break;
+                        }
+                        _ = tokio::time::sleep(idle_timeout.saturating_sub(idle_for)), if !shutting_down => {
+                            if activity.idle_for() >= idle_timeout {
+                                debug!("Closing connection idle for {:?}", idle_timeout);
+                                connection.as_mut().graceful_shutdown();
+                                shutting_down = true;
+                            }
+                        }
+                        _ = shutdown.cancelled(), if !shutting_down => {
+                            connection.as_mut().graceful_shutdown();
+                            shutting_down = true;
+                        }
+                    }
+                }
+
+                if let Some(on_connection_close) = on_connection_close {
+                    on_connection_close(
+                        peer,
+                        requests.load(Ordering::Relaxed),
+                        opened.elapsed(),
+                    );
+                }
+            });
+        }
+
+        info!("Shutting down, waiting for open connections to finish ...");
+
+        connections.close();
+        connections.wait().await;
+    }
+
+    // Binds the listening socket, with `SO_REUSEPORT` when enabled so several
+    // processes can share the port.
+    pub(super) fn bind_listener(&self, addr: SocketAddr) -> std::io::Result<TcpListener> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+        // like tokio's own bind, as on Windows it would let others steal the port
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+
+        if self.reuse_port {
+            #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+            socket.set_reuse_port(true)?;
+
+            #[cfg(not(all(unix, not(any(target_os = "solaris", target_os = "illumos")))))]
+            warn!("SO_REUSEPORT isn't supported on this platform, ignoring it.");
+        }
+
+        socket.set_nonblocking(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(1024)?;
+
+        TcpListener::from_std(socket.into())
+    }
+
+    pub fn run(&self) {
+        if !self.answers_anything() {
+            warn!("No routes registered, every request will get a 404.");
+        }
+
+        let mut builder = match self.worker_threads {
+            Some(0) => tokio::runtime::Builder::new_current_thread(),
+            Some(threads) => {
+                let mut builder = tokio::runtime::Builder::new_multi_thread();
+                builder.worker_threads(threads);
+                builder
+            }
+            None => tokio::runtime::Builder::new_multi_thread(),
+        };
+
+        if let Ok(rt) = builder.enable_all().build() {
+            info!("Listening on {}:{} ...", self.ip, self.port);
+            rt.block_on(self.listen());
+        } else {
+            error!("Could not start runtime.");
+        }
+    }
+}
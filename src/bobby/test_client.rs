@@ -0,0 +1,163 @@
+use super::*;
+use http_body_util::Full;
+use hyper::client::conn::http1::{self, SendRequest};
+use hyper_util::rt::TokioIo;
+use tokio::{net::TcpStream, runtime::Runtime};
+
+/// Runs a copy of the app on an ephemeral local port and talks to it over
+/// real HTTP/1.1, for end-to-end tests that cover what in-process dispatch
+/// skips, like header encoding and keep-alive. Requests reuse one connection
+/// for as long as the server keeps it open. The server stops when the client
+/// is dropped.
+///
+/// The helpers block, so use them from plain `#[test]` functions rather than
+/// from inside an async runtime.
+pub struct TestClient {
+    runtime: Runtime,
+    addr: SocketAddr,
+    sender: Mutex<Option<SendRequest<Full<Bytes>>>>,
+}
+
+impl TestClient {
+    /// Starts serving `app` on `127.0.0.1` at a port picked by the OS, with
+    /// its configured address and TLS setup ignored, so it speaks plain HTTP.
+    pub fn new(app: &Bobby) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+
+        let app = app.clone();
+        #[cfg(feature = "tls")]
+        let app = Bobby { tls: None, ..app };
+
+        let listener = {
+            let _guard = runtime.enter();
+            app.bind_listener(SocketAddr::from(([127, 0, 0, 1], 0)))?
+        };
+        let addr = listener.local_addr()?;
+
+        runtime.spawn(async move { app.serve(listener).await });
+
+        Ok(TestClient {
+            runtime,
+            addr,
+            sender: Mutex::new(None),
+        })
+    }
+
+    /// The address the app is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn get(&self, path: &str) -> std::io::Result<TestResponse> {
+        self.request(hyper::Method::GET, path, Bytes::new())
+    }
+
+    pub fn post(&self, path: &str, body: impl Into<Bytes>) -> std::io::Result<TestResponse> {
+        self.request(hyper::Method::POST, path, body.into())
+    }
+
+    /// Sends a request with any method, e.g. for `PUT` or `DELETE`.
+    pub fn request(
+        &self,
+        method: hyper::Method,
+        path: &str,
+        body: Bytes,
+    ) -> std::io::Result<TestResponse> {
+        let request = hyper::Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::HOST, self.addr.to_string())
+            .body(Full::new(body))
+            .map_err(std::io::Error::other)?;
+
+        self.send(request)
+    }
+
+    /// Sends a request built by hand, for when headers matter. A `Host`
+    /// header is added when missing.
+    pub fn send(&self, mut request: hyper::Request<Full<Bytes>>) -> std::io::Result<TestResponse> {
+        if !request.headers().contains_key(header::HOST)
+            && let Ok(host) = header::HeaderValue::from_str(&self.addr.to_string())
+        {
+            request.headers_mut().insert(header::HOST, host);
+        }
+
+        self.runtime.block_on(async {
+            let mut sender = self.ready_sender().await?;
+            let response = sender.send_request(request).await;
+
+            *self.sender.lock().unwrap() = Some(sender);
+
+            let (parts, body) = response.map_err(std::io::Error::other)?.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(std::io::Error::other)?
+                .to_bytes();
+
+            Ok(TestResponse {
+                status: parts.status.as_u16(),
+                headers: parts.headers,
+                body,
+            })
+        })
+    }
+
+    // The kept-alive connection when it's still usable, a new one otherwise.
+    async fn ready_sender(&self) -> std::io::Result<SendRequest<Full<Bytes>>> {
+        let kept = self.sender.lock().unwrap().take();
+
+        if let Some(mut sender) = kept
+            && sender.ready().await.is_ok()
+        {
+            return Ok(sender);
+        }
+
+        let stream = TcpStream::connect(self.addr).await?;
+        let (sender, connection) = http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(std::io::Error::other)?;
+
+        tokio::task::spawn(async move {
+            if let Err(err) = connection.await {
+                debug!("Test client connection closed: {}", err);
+            }
+        });
+
+        Ok(sender)
+    }
+}
+
+/// A response received by `TestClient`, with its body read in full.
+pub struct TestResponse {
+    status: u16,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// The first value of the header `name`, looked up case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// The body as text, with invalid UTF-8 replaced.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
@@ -0,0 +1,240 @@
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt, TryStreamExt, stream};
+use http_body_util::{BodyExt, Full, StreamBody, combinators::UnsyncBoxBody};
+use hyper::body::{Frame, SizeHint};
+use log::warn;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type HttpBody = UnsyncBoxBody<Bytes, BoxError>;
+
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, BoxError>> + Send>>;
+
+pub enum Body {
+    Text(String),
+    Bytes(Bytes),
+    Stream(BodyStream),
+}
+
+impl Body {
+    pub fn empty() -> Self {
+        Body::Bytes(Bytes::new())
+    }
+
+    pub fn stream<S, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<BoxError> + 'static,
+    {
+        Body::Stream(Box::pin(stream.map_err(Into::into)))
+    }
+
+    /// A streaming body fed by the returned `BodyWriter`, for handlers that
+    /// produce their output over time (server-sent events, progress, ...).
+    pub fn channel() -> (BodyWriter, Self) {
+        let (sender, receiver) = mpsc::channel(16);
+        let chunks = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        });
+
+        let writer = BodyWriter {
+            sender,
+            buffer: BytesMut::new(),
+        };
+
+        (writer, Body::Stream(Box::pin(chunks)))
+    }
+
+    // Length in bytes, if known up front. Streams have no known length.
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Body::Text(text) => Some(text.len()),
+            Body::Bytes(bytes) => Some(bytes.len()),
+            Body::Stream(_) => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// The body's bytes, or `None` for a stream, which can only be read by
+    /// consuming it.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Body::Text(text) => Some(text.as_bytes()),
+            Body::Bytes(bytes) => Some(bytes),
+            Body::Stream(_) => None,
+        }
+    }
+
+    pub(crate) fn into_http_body(self) -> HttpBody {
+        match self {
+            Body::Text(text) => Full::new(Bytes::from(text))
+                .map_err(|never| match never {})
+                .boxed_unsync(),
+            Body::Bytes(bytes) => Full::new(bytes)
+                .map_err(|never| match never {})
+                .boxed_unsync(),
+            Body::Stream(stream) => {
+                BodyExt::boxed_unsync(StreamBody::new(stream.map(|chunk| chunk.map(Frame::data))))
+            }
+        }
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        Body::Text(text)
+    }
+}
+
+impl From<&str> for Body {
+    fn from(text: &str) -> Self {
+        Body::Text(String::from(text))
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Body::Bytes(Bytes::from(bytes))
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(bytes: Bytes) -> Self {
+        Body::Bytes(bytes)
+    }
+}
+
+/// Writes into a body created with `Body::channel`. Writes are buffered until
+/// `flush` is called, which sends everything written so far to the client as
+/// one chunk.
+pub struct BodyWriter {
+    sender: mpsc::Sender<Result<Bytes, BoxError>>,
+    buffer: BytesMut,
+}
+
+impl BodyWriter {
+    pub fn write(&mut self, data: impl AsRef<[u8]>) {
+        self.buffer.extend_from_slice(data.as_ref());
+    }
+
+    pub async fn flush(&mut self) -> Result<(), BodyClosed> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = self.buffer.split().freeze();
+
+        self.sender.send(Ok(chunk)).await.map_err(|_| BodyClosed)
+    }
+
+    /// Writes `data` and flushes it right away.
+    pub async fn send(&mut self, data: impl AsRef<[u8]>) -> Result<(), BodyClosed> {
+        self.write(data);
+        self.flush().await
+    }
+
+    /// Flushes what's left and ends the body.
+    pub async fn close(mut self) -> Result<(), BodyClosed> {
+        self.flush().await
+    }
+}
+
+impl Drop for BodyWriter {
+    // best effort, as there's no way to wait for room in the channel here,
+    // so what doesn't fit is lost. `close` is the way to not lose it
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let len = self.buffer.len();
+
+        if let Err(mpsc::error::TrySendError::Full(_)) =
+            self.sender.try_send(Ok(self.buffer.split().freeze()))
+        {
+            warn!(
+                "Response body writer dropped with {} unsent bytes, which were lost",
+                len
+            );
+        }
+    }
+}
+
+/// The client went away, or the response carrying the body was dropped.
+pub struct BodyClosed;
+
+impl std::fmt::Display for BodyClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Response body closed")
+    }
+}
+
+impl std::fmt::Debug for BodyClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for BodyClosed {}
+
+// Wraps a response body to cancel the request's token when the body is
+// dropped before it was sent in full, which is what happens when the client
+// disconnects mid-response.
+pub(crate) struct CancelOnDrop {
+    inner: HttpBody,
+    token: CancellationToken,
+    done: bool,
+}
+
+impl CancelOnDrop {
+    pub(crate) fn new(inner: HttpBody, token: CancellationToken) -> Self {
+        CancelOnDrop {
+            inner,
+            token,
+            done: false,
+        }
+    }
+}
+
+impl hyper::body::Body for CancelOnDrop {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+
+        if matches!(poll, Poll::Ready(None)) || self.inner.is_end_stream() {
+            self.done = true;
+        }
+
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if !self.done && !hyper::body::Body::is_end_stream(&self.inner) {
+            self.token.cancel();
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use bytes::Bytes;
+use hyper::{HeaderMap, header::COOKIE};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// A response as stored in the cache, with its body fully buffered so it can
+// be handed out any number of times.
+#[derive(Clone)]
+pub(crate) struct CachedResponse {
+    pub(crate) status: u16,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Bytes,
+}
+
+struct Entry {
+    response: CachedResponse,
+    // the request header values named by the response's `Vary` header
+    vary: Vec<(String, Option<String>)>,
+    expires: Instant,
+    last_used: u64,
+}
+
+// A bounded, least-recently-used cache of responses keyed by path and query.
+// Eviction scans for the oldest entry, which is fine at the sizes a single
+// process would keep in memory.
+pub(crate) struct ResponseCache {
+    capacity: usize,
+    entries: HashMap<String, Entry>,
+    clock: u64,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ResponseCache {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+
+        while self.entries.len() > capacity {
+            self.evict_oldest();
+        }
+    }
+
+    pub(crate) fn get(
+        &mut self,
+        key: &str,
+        headers: &HeaderMap,
+        now: Instant,
+    ) -> Option<CachedResponse> {
+        self.clock += 1;
+
+        let entry = self.entries.get_mut(key)?;
+
+        if entry.expires <= now {
+            self.entries.remove(key);
+            return None;
+        }
+
+        let varies = entry
+            .vary
+            .iter()
+            .any(|(name, value)| header_value(headers, name) != *value);
+
+        // a response that doesn't vary on `Cookie` was made without one, and
+        // isn't meant for someone sending theirs
+        if varies || (headers.contains_key(COOKIE) && !entry.varies_on("cookie")) {
+            return None;
+        }
+
+        entry.last_used = self.clock;
+
+        Some(entry.response.clone())
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        key: String,
+        response: CachedResponse,
+        headers: &HeaderMap,
+        ttl: Duration,
+        now: Instant,
+    ) {
+        let vary_names: Vec<String> = response
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("vary"))
+            .flat_map(|(_, value)| value.split(','))
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        // `Vary: *` means no two requests can share the response
+        if self.capacity == 0 || vary_names.iter().any(|name| name == "*") {
+            return;
+        }
+
+        // the same goes for one made for a request with cookies, unless it
+        // says it depends on them
+        if headers.contains_key(COOKIE) && !vary_names.iter().any(|name| name == "cookie") {
+            return;
+        }
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.evict_oldest();
+        }
+
+        let vary = vary_names
+            .into_iter()
+            .map(|name| {
+                let value = header_value(headers, &name);
+                (name, value)
+            })
+            .collect();
+
+        self.clock += 1;
+        self.entries.insert(
+            key,
+            Entry {
+                response,
+                vary,
+                expires: now + ttl,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+
+        if let Some(oldest) = oldest {
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+impl Entry {
+    fn varies_on(&self, name: &str) -> bool {
+        self.vary.iter().any(|(vary, _)| vary == name)
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
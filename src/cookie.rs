@@ -0,0 +1,171 @@
+use hyper::{HeaderMap, header};
+use std::{collections::HashMap, time::Duration};
+
+// Parses every `Cookie` header into name/value pairs. Clients (and HTTP/2,
+// which may split cookies into one header per pair) can send several, so all
+// of them are read, not just the first. When a name repeats the first one
+// wins, as browsers send the most specific cookie first.
+pub(crate) fn parse_cookies(headers: &HeaderMap) -> HashMap<String, String> {
+    let mut cookies = HashMap::new();
+
+    for value in headers.get_all(header::COOKIE) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+
+        for pair in value.split(';') {
+            let Some((name, value)) = pair.split_once('=') else {
+                continue;
+            };
+
+            let name = name.trim();
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+
+            if !name.is_empty() {
+                cookies
+                    .entry(String::from(name))
+                    .or_insert_with(|| String::from(value));
+            }
+        }
+    }
+
+    cookies
+}
+
+/// A cookie to send with `Response::with_cookie`. Only the name and value
+/// are required, every attribute is left off unless set.
+#[derive(Clone, Debug)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    http_only: bool,
+    secure: bool,
+    same_site: Option<SameSite>,
+}
+
+/// The `SameSite` attribute of a cookie, controlling whether it's sent with
+/// requests coming from other sites.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    /// Browsers only accept this together with `Secure`.
+    None,
+}
+
+impl Cookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Cookie {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            max_age: None,
+            http_only: false,
+            secure: false,
+            same_site: None,
+        }
+    }
+
+    /// A cookie that makes the browser delete the one called `name`. It has
+    /// to have the same `path` and `domain` as the cookie it removes.
+    pub fn removal(name: impl Into<String>) -> Self {
+        Cookie::new(name, "").max_age(Duration::ZERO)
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// How long the browser keeps the cookie, in whole seconds. Without it
+    /// the cookie is gone when the browser closes.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Hides the cookie from JavaScript.
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    /// Only sends the cookie over HTTPS.
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    // Whether the cookie can be sent as is: the name is an HTTP token, the
+    // value has no whitespace, quotes, commas, semicolons or backslashes, and
+    // the attributes can't break out of their place in the header.
+    pub(crate) fn is_valid(&self) -> bool {
+        let name = !self.name.is_empty()
+            && self
+                .name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+        let value = self
+            .value
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !matches!(b, b'"' | b',' | b';' | b'\\'));
+        let attributes = [&self.path, &self.domain]
+            .into_iter()
+            .flatten()
+            .all(|attribute| !attribute.contains(';') && !attribute.contains(char::is_control));
+
+        name && value && attributes
+    }
+}
+
+impl std::fmt::Display for Cookie {
+    // Formats the cookie as the value of a `Set-Cookie` header.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+
+        match self.same_site {
+            Some(SameSite::Strict) => write!(f, "; SameSite=Strict"),
+            Some(SameSite::Lax) => write!(f, "; SameSite=Lax"),
+            Some(SameSite::None) => write!(f, "; SameSite=None"),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{Next, Request, Response};
+use std::time::Duration;
+
+/// Cross-origin resource sharing settings for `Bobby::cors`. Nothing is
+/// allowed until it's set, apart from the `GET`, `HEAD` and `POST` methods.
+#[derive(Clone, Debug)]
+pub struct Cors {
+    origins: Vec<String>,
+    methods: Vec<String>,
+    headers: Vec<String>,
+    expose_headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Cors::new()
+    }
+}
+
+impl Cors {
+    pub fn new() -> Self {
+        Cors {
+            origins: vec![],
+            methods: vec![
+                String::from("GET"),
+                String::from("HEAD"),
+                String::from("POST"),
+            ],
+            headers: vec![],
+            expose_headers: vec![],
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// Allows requests from `origin`, e.g. `https://example.com`, or from
+    /// any origin with `*`. Can be called more than once.
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origins.push(origin.into());
+        self
+    }
+
+    /// Allows requests from each of `origins`.
+    pub fn allow_origins(mut self, origins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.origins.extend(origins.into_iter().map(Into::into));
+        self
+    }
+
+    /// The methods cross-origin requests can use, replacing the default of
+    /// `GET`, `HEAD` and `POST`.
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.methods = methods.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The request headers cross-origin requests can send, or `*` for any
+    /// the browser asks for.
+    pub fn allow_headers(mut self, headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The response headers the page's scripts can read, on top of the
+    /// handful browsers always expose.
+    pub fn expose_headers(mut self, headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.expose_headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Lets cross-origin requests carry cookies and other credentials.
+    pub fn allow_credentials(mut self) -> Self {
+        self.credentials = true;
+        self
+    }
+
+    /// How long browsers can cache a preflight response, in whole seconds.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub(crate) fn any_origin(&self) -> bool {
+        self.origins.iter().any(|origin| origin == "*")
+    }
+
+    pub(crate) fn credentials(&self) -> bool {
+        self.credentials
+    }
+
+    // The `Access-Control-Allow-Origin` value for a request from `origin`, if
+    // it's allowed at all. `Bobby::cors` refuses `*` along with credentials,
+    // so a listed origin is the only way to get those.
+    fn allowed_origin(&self, origin: &str) -> Option<String> {
+        if self.any_origin() {
+            return Some(String::from("*"));
+        }
+
+        self.origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+            .then(|| String::from(origin))
+    }
+
+    // Whether the response depends on the request's `Origin`, which caches
+    // need to be told with `Vary`.
+    fn varies_by_origin(&self) -> bool {
+        !self.any_origin()
+    }
+
+    // Answers preflight requests by itself, and adds the CORS headers to the
+    // response of everything else coming from another origin.
+    pub(crate) fn handle(&self, req: Request, next: Next) -> Response {
+        let Some(origin) = req.header("Origin").map(String::from) else {
+            let response = next.run(req);
+
+            if self.varies_by_origin() {
+                return response.append_header("Vary", "Origin");
+            }
+
+            return response;
+        };
+
+        let allowed = self.allowed_origin(&origin);
+
+        if is_preflight(&req) {
+            return self.preflight(&req, allowed);
+        }
+
+        let mut response = next.run(req);
+
+        if self.varies_by_origin() {
+            response = response.append_header("Vary", "Origin");
+        }
+
+        let Some(allowed) = allowed else {
+            return response;
+        };
+
+        response = response.with_header("Access-Control-Allow-Origin", allowed);
+
+        if self.credentials {
+            response = response.with_header("Access-Control-Allow-Credentials", "true");
+        }
+
+        if !self.expose_headers.is_empty() {
+            response = response.with_header(
+                "Access-Control-Expose-Headers",
+                self.expose_headers.join(", "),
+            );
+        }
+
+        response
+    }
+
+    // A `204` telling the browser what the actual request may do. A
+    // disallowed origin gets no CORS headers at all, which the browser takes
+    // as a refusal.
+    fn preflight(&self, req: &Request, allowed: Option<String>) -> Response {
+        let mut response = Response::html("")
+            .with_status(204)
+            .without_header("Content-Type")
+            .append_header(
+                "Vary",
+                "Origin, Access-Control-Request-Method, Access-Control-Request-Headers",
+            );
+
+        let Some(allowed) = allowed else {
+            return response;
+        };
+
+        response = response
+            .with_header("Access-Control-Allow-Origin", allowed)
+            .with_header("Access-Control-Allow-Methods", self.methods.join(", "));
+
+        // `*` isn't taken literally along with credentials, so the requested
+        // headers are echoed back instead
+        let headers = if self.headers.iter().any(|header| header == "*") {
+            req.header("Access-Control-Request-Headers")
+                .map(String::from)
+        } else {
+            (!self.headers.is_empty()).then(|| self.headers.join(", "))
+        };
+
+        if let Some(headers) = headers {
+            response = response.with_header("Access-Control-Allow-Headers", headers);
+        }
+
+        if self.credentials {
+            response = response.with_header("Access-Control-Allow-Credentials", "true");
+        }
+
+        if let Some(max_age) = self.max_age {
+            response =
+                response.with_header("Access-Control-Max-Age", max_age.as_secs().to_string());
+        }
+
+        response
+    }
+}
+
+// A browser asking whether it may make a cross-origin request, rather than
+// making one.
+pub(crate) fn is_preflight(req: &Request) -> bool {
+    req.method() == hyper::Method::OPTIONS
+        && req.header("Origin").is_some()
+        && req.header("Access-Control-Request-Method").is_some()
+}
@@ -0,0 +1,28 @@
+// Formats one CSV record, quoting fields that contain a comma, quote or line
+// break and doubling any quotes inside them, per RFC 4180.
+pub(crate) fn record<I>(fields: I) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut line = String::new();
+
+    for (i, field) in fields.into_iter().enumerate() {
+        let field = field.as_ref();
+
+        if i > 0 {
+            line.push(',');
+        }
+
+        if field.contains([',', '"', '\n', '\r']) {
+            line.push('"');
+            line.push_str(&field.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(field);
+        }
+    }
+
+    line.push_str("\r\n");
+    line
+}
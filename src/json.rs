@@ -0,0 +1,144 @@
+#[derive(Clone, Copy)]
+pub(crate) struct JsonLimits {
+    pub(crate) max_depth: usize,
+    pub(crate) max_values: usize,
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        JsonLimits {
+            max_depth: 64,
+            max_values: 100_000,
+        }
+    }
+}
+
+impl JsonLimits {
+    // Scans the raw JSON for nesting depth and value count without parsing it,
+    // so a small but pathological payload is rejected before serde allocates
+    // anything for it. serde_json's own recursion limit is fixed at 128, so
+    // it only backs this up. Both counts are approximations, as the JSON isn't
+    // validated here: values are containers plus the separators between
+    // elements, which is cheap to count and close enough for a limit, and
+    // anything in a string, escaped quotes included, is skipped.
+    pub(crate) fn check(&self, json: &[u8]) -> Result<(), JsonError> {
+        let mut depth = 0;
+        let mut values = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        for &byte in json {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+
+                continue;
+            }
+
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => {
+                    depth += 1;
+                    values += 1;
+
+                    if depth > self.max_depth {
+                        return Err(JsonError::TooDeep);
+                    }
+                }
+                b'}' | b']' => depth -= usize::from(depth > 0),
+                b',' => values += 1,
+                _ => {}
+            }
+
+            if values > self.max_values {
+                return Err(JsonError::TooManyValues);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub enum JsonError {
+    TooDeep,
+    TooManyValues,
+    Invalid(serde_json::Error),
+}
+
+impl JsonError {
+    /// The status to respond with: 413 for bodies over the limits and 400
+    /// for bodies that aren't valid JSON for the target type.
+    pub fn status(&self) -> u16 {
+        match self {
+            JsonError::TooDeep | JsonError::TooManyValues => 413,
+            JsonError::Invalid(_) => 400,
+        }
+    }
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::TooDeep => write!(f, "JSON body is nested too deeply"),
+            JsonError::TooManyValues => write!(f, "JSON body has too many values"),
+            JsonError::Invalid(err) => write!(f, "Invalid JSON body: {}", err),
+        }
+    }
+}
+
+impl std::fmt::Debug for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_depth: usize, max_values: usize) -> JsonLimits {
+        JsonLimits {
+            max_depth,
+            max_values,
+        }
+    }
+
+    #[test]
+    fn limits_the_nesting_depth() {
+        assert!(limits(3, 100).check(b"[[[1]]]").is_ok());
+        assert!(matches!(
+            limits(3, 100).check(b"[[[[1]]]]"),
+            Err(JsonError::TooDeep)
+        ));
+        assert!(
+            limits(2, 100)
+                .check(br#"{"a": [1], "b": {"c": 2}}"#)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn limits_the_number_of_values() {
+        assert!(limits(10, 4).check(b"[1, 2, 3]").is_ok());
+        assert!(matches!(
+            limits(10, 4).check(b"[1, 2, 3, 4, 5]"),
+            Err(JsonError::TooManyValues)
+        ));
+    }
+
+    #[test]
+    fn skips_brackets_and_separators_in_strings() {
+        assert!(limits(1, 2).check(br#"["[[[{{{,,,"]"#).is_ok());
+        assert!(limits(1, 2).check(br#"["a\"[[[,,\\", "b"]"#).is_ok());
+        assert!(matches!(
+            limits(1, 2).check(br#"["a\\", [1]]"#),
+            Err(JsonError::TooDeep)
+        ));
+    }
+}
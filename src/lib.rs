@@ -1,3 +1,35 @@
+mod access_log;
 mod bobby;
+mod body;
+mod cache;
+mod cookie;
+mod cors;
+mod csv;
+#[cfg(feature = "server")]
+mod idle;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+mod openapi;
+mod pattern;
+mod query;
+mod route_index;
+#[cfg(feature = "fs")]
+mod static_files;
+#[cfg(feature = "tls")]
+mod tls;
+mod util;
 
+pub use access_log::*;
 pub use bobby::*;
+pub use body::*;
+pub use cookie::{Cookie, SameSite};
+pub use cors::Cors;
+#[cfg(feature = "json")]
+pub use json::*;
+pub use pattern::{RouteConflict, RouteError, TrailingSlash};
+#[cfg(feature = "fs")]
+pub use static_files::*;
+#[cfg(feature = "tls")]
+pub use tls::TlsError;
+pub use tokio_util::sync::CancellationToken;
@@ -0,0 +1,84 @@
+use crate::{Route, pattern};
+use serde_json::{Map, Value, json};
+
+#[derive(Clone)]
+pub(crate) struct OpenApi {
+    pub(crate) path: String,
+    pub(crate) title: String,
+    pub(crate) version: String,
+}
+
+pub(crate) fn spec(routes: &[Route], title: &str, version: &str) -> Value {
+    let mut paths = Map::new();
+
+    // redirect routes, the HEAD of each included, only point elsewhere
+    for route in routes.iter().filter(|route| route.redirect.is_none()) {
+        let (path, params) = convert_path(&route.path);
+        let mut operation = Map::new();
+
+        if let Some(summary) = &route.summary {
+            operation.insert(String::from("summary"), json!(summary));
+        }
+
+        if !params.is_empty() {
+            let parameters: Vec<Value> = params
+                .iter()
+                .map(|name| {
+                    json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" }
+                    })
+                })
+                .collect();
+
+            operation.insert(String::from("parameters"), Value::Array(parameters));
+        }
+
+        operation.insert(
+            String::from("responses"),
+            json!({ "default": { "description": "Response" } }),
+        );
+
+        let item = paths
+            .entry(path)
+            .or_insert_with(|| Value::Object(Map::new()));
+
+        if let Value::Object(item) = item {
+            item.insert(
+                route.method.as_str().to_lowercase(),
+                Value::Object(operation),
+            );
+        }
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": { "title": title, "version": version },
+        "paths": paths
+    })
+}
+
+// Turns a route pattern into an OpenAPI path template and its param names.
+// OpenAPI has no optional or wildcard path params, so `{id?}` and `{*rest}`
+// both become plain `{id}` and `{rest}` params. Constraints are left out too.
+fn convert_path(pattern: &str) -> (String, Vec<String>) {
+    let mut params = vec![];
+    let mut segments = vec![];
+
+    for segment in pattern.split('/').filter(|s| !s.is_empty()) {
+        match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(inner) => {
+                let inner = inner.trim_start_matches(['*', '+']);
+                let name = pattern::param_parts(inner).0.to_string();
+
+                segments.push(format!("{{{}}}", name));
+                params.push(name);
+            }
+            None => segments.push(segment.to_string()),
+        }
+    }
+
+    (format!("/{}", segments.join("/")), params)
+}
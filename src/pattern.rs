@@ -0,0 +1,259 @@
+use regex::Regex;
+use std::collections::HashSet;
+
+/// Why a route couldn't be registered.
+pub enum RouteError {
+    /// A segment has a `{` or `}` that doesn't wrap the whole segment.
+    UnbalancedBraces(String),
+    /// A param has no name, or one with characters other than letters,
+    /// digits, `_` and `-`.
+    InvalidParamName(String),
+    /// A `{*name}` or `{+name}` wildcard isn't the last segment.
+    WildcardNotLast(String),
+    /// Two params share a name.
+    DuplicateParam(String),
+    /// A param's `{name:constraint}` isn't a valid regular expression.
+    InvalidConstraint(String),
+    /// The limit set with `Bobby::with_max_routes` was reached.
+    TooManyRoutes(usize),
+    /// A route for the same method with an equally specific pattern, given
+    /// here, was registered before, and `RouteConflict::Reject` is set.
+    Conflict(String),
+    /// A redirect route's status isn't a `3xx` one.
+    InvalidRedirectStatus(u16),
+}
+
+/// What to do on registering a route that's just as specific as one already
+/// registered for the same method and host, e.g. `/users/{id}` after
+/// `/users/{name}`. Either way the first registered one is what matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteConflict {
+    /// Register it silently.
+    Allow,
+    /// Register it and log a warning, as it can never match. The default.
+    Warn,
+    /// Refuse to register it.
+    Reject,
+}
+
+/// How a trailing slash in the request path is matched against routes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// `/users` and `/users/` both match either route. The default.
+    Relaxed,
+    /// `/users` only matches `/users`, and `/users/` only `/users/`.
+    Strict,
+    /// Like `Strict`, but a request for the other form of a route's path is
+    /// redirected to the one the route was registered with, with a `301`
+    /// for `GET` and `HEAD` and a `308` for everything else.
+    Redirect,
+}
+
+impl std::fmt::Display for RouteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteError::UnbalancedBraces(segment) => {
+                write!(f, "Unbalanced braces in segment \"{}\"", segment)
+            }
+            RouteError::InvalidParamName(segment) => {
+                write!(f, "Invalid param name in segment \"{}\"", segment)
+            }
+            RouteError::WildcardNotLast(segment) => {
+                write!(f, "Wildcard \"{}\" must be the last segment", segment)
+            }
+            RouteError::DuplicateParam(name) => write!(f, "Duplicate param \"{}\"", name),
+            RouteError::InvalidConstraint(segment) => {
+                write!(f, "Invalid constraint in segment \"{}\"", segment)
+            }
+            RouteError::TooManyRoutes(max) => write!(f, "Route limit of {} reached", max),
+            RouteError::Conflict(existing) => {
+                write!(f, "Conflicts with \"{}\", registered before", existing)
+            }
+            RouteError::InvalidRedirectStatus(status) => {
+                write!(f, "Redirect status {} isn't a 3xx one", status)
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for RouteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+// Checks a route pattern's syntax: every segment is either a literal without
+// braces or a whole `{name}`, `{name?}`, `{*name}` or `{+name}`, the first
+// two optionally followed by a `:constraint`, wildcards come last, and param
+// names are unique.
+pub(crate) fn validate(path: &str) -> Result<(), RouteError> {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let mut names = HashSet::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        let Some(inner) = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) else {
+            if segment.contains(['{', '}']) {
+                return Err(RouteError::UnbalancedBraces(String::from(*segment)));
+            }
+
+            continue;
+        };
+
+        let (name, wildcard) = match inner.strip_prefix(['*', '+']) {
+            Some(name) => (name, true),
+            None => (param_parts(inner).0, false),
+        };
+
+        if name.contains(['{', '}']) {
+            return Err(RouteError::UnbalancedBraces(String::from(*segment)));
+        }
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(RouteError::InvalidParamName(String::from(*segment)));
+        }
+
+        if wildcard && i + 1 < segments.len() {
+            return Err(RouteError::WildcardNotLast(String::from(*segment)));
+        }
+
+        if !names.insert(name) {
+            return Err(RouteError::DuplicateParam(String::from(name)));
+        }
+    }
+
+    Ok(())
+}
+
+// Splits the inside of a `{...}` param segment, `name`, `name?`,
+// `name:constraint` or `name?:constraint`, into the name, whether it's
+// optional and the constraint.
+pub(crate) fn param_parts(inner: &str) -> (&str, bool, Option<&str>) {
+    let (name, constraint) = match inner.split_once(':') {
+        Some((name, constraint)) => (name, Some(constraint)),
+        None => (inner, None),
+    };
+
+    match name.strip_suffix('?') {
+        Some(name) => (name, true, constraint),
+        None => (name, false, constraint),
+    }
+}
+
+// Compiles the constraints of a pattern's params, anchored so they have to
+// match the whole segment.
+pub(crate) fn constraints(path: &str) -> Result<Vec<(String, Regex)>, RouteError> {
+    let mut constraints = vec![];
+
+    for segment in path.split('/') {
+        let Some(inner) = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) else {
+            continue;
+        };
+
+        if inner.starts_with(['*', '+']) {
+            continue;
+        }
+
+        if let (name, _, Some(constraint)) = param_parts(inner) {
+            let regex = Regex::new(&format!("^(?:{})$", constraint))
+                .map_err(|_| RouteError::InvalidConstraint(String::from(segment)))?;
+
+            constraints.push((String::from(name), regex));
+        }
+    }
+
+    Ok(constraints)
+}
+
+// Rewrites the `{name:*}` and `{name:+}` spellings of wildcards into
+// `{*name}` and `{+name}`, which is what the rest of the routing works with.
+pub(crate) fn normalize(path: &str) -> String {
+    path.split('/')
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(inner) if inner.ends_with(":*") || inner.ends_with(":+") => {
+                    let (name, kind) = inner.split_at(inner.len() - 2);
+
+                    format!("{{{}{}}}", &kind[1..], name)
+                }
+                _ => String::from(segment),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// The pattern with param names left out, so two patterns with the same shape
+// match exactly the same paths, e.g. `/users/{}` for `/users/{id}`, or
+// `/users/{:\d+}` for `/users/{id:\d+}`.
+pub(crate) fn shape(path: &str) -> String {
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(inner) if inner.starts_with('*') => String::from("{*}"),
+                Some(inner) if inner.starts_with('+') => String::from("{+}"),
+                Some(inner) => {
+                    let (_, optional, constraint) = param_parts(inner);
+                    let optional = if optional { "?" } else { "" };
+
+                    match constraint {
+                        Some(constraint) => format!("{{{}:{}}}", optional, constraint),
+                        None => format!("{{{}}}", optional),
+                    }
+                }
+                None => String::from(segment),
+            },
+        )
+        .collect();
+
+    format!("/{}", segments.join("/"))
+}
+
+// How specific each segment of a pattern is, for picking between routes that
+// match the same path, from a literal down to a `{*name}` wildcard.
+pub(crate) fn specificity(path: &str) -> Vec<u8> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(inner) if inner.starts_with('*') => 0,
+                Some(inner) if inner.starts_with('+') => 1,
+                Some(inner) => match param_parts(inner) {
+                    (_, true, None) => 2,
+                    (_, true, Some(_)) => 3,
+                    (_, false, None) => 4,
+                    (_, false, Some(_)) => 5,
+                },
+                None => LITERAL,
+            },
+        )
+        .collect()
+}
+
+const LITERAL: u8 = 6;
+
+// Whether a pattern of specificity `a` is more specific than one of `b`,
+// looking at one segment after the other until they differ. Where one pattern
+// has run out of segments it counts as a literal, as it matched the path
+// exactly where the other needed an optional param or wildcard for the rest.
+pub(crate) fn more_specific(a: &[u8], b: &[u8]) -> bool {
+    let len = a.len().max(b.len());
+    let rank = |ranks: &[u8], i: usize| ranks.get(i).copied().unwrap_or(LITERAL);
+
+    (0..len)
+        .map(|i| rank(a, i).cmp(&rank(b, i)))
+        .find(|ordering| ordering.is_ne())
+        .is_some_and(|ordering| ordering.is_gt())
+}
+
+// Whether a path ends in a slash, not counting the root.
+pub(crate) fn has_trailing_slash(path: &str) -> bool {
+    path.len() > 1 && path.ends_with('/')
+}
@@ -0,0 +1,63 @@
+use crate::util::{hex_byte, percent_decode};
+use std::collections::HashMap;
+
+// Parses a query string into name/value pairs, with `+` as a space. Malformed
+// percent sequences (`%zz`, a truncated `%a`) are left as written while the
+// well-formed ones around them are still decoded, and a component that
+// decodes to invalid UTF-8 is kept as written. When a name repeats, the first
+// value wins.
+pub(crate) fn parse_query(query: &str) -> HashMap<String, String> {
+    let mut queries = HashMap::new();
+
+    for (name, value) in pairs(query) {
+        queries
+            .entry(decode_lenient(name))
+            .or_insert_with(|| decode_lenient(value));
+    }
+
+    queries
+}
+
+// Whether every name and value in the query decodes cleanly, for the strict
+// policy enabled with `Bobby::with_strict_query`.
+pub(crate) fn is_well_formed(query: &str) -> bool {
+    pairs(query).all(|(name, value)| decode(name).is_some() && decode(value).is_some())
+}
+
+fn pairs(query: &str) -> impl Iterator<Item = (&str, &str)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+}
+
+fn decode(input: &str) -> Option<String> {
+    percent_decode(&input.replace('+', " "))
+}
+
+fn decode_lenient(input: &str) -> String {
+    let input = input.replace('+', " ");
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| input.get(i + 1..i + 3))
+            .flatten()
+            .and_then(hex_byte);
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).unwrap_or(input)
+}
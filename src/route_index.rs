@@ -0,0 +1,97 @@
+use std::{borrow::Cow, collections::HashMap};
+
+// Indexes routes by position so matching can skip most of them. Routes without
+// params are keyed by their normalized path, so a request only looks at the
+// static routes with its exact path, plus every parametric route, which still
+// goes through segment matching. Positions come out in registration order, so
+// the first registered of equally specific matches wins.
+#[derive(Clone, Default)]
+pub(crate) struct RouteIndex {
+    exact: HashMap<String, Vec<usize>>,
+    parametric: Vec<usize>,
+}
+
+impl RouteIndex {
+    pub(crate) fn new<'a>(paths: impl Iterator<Item = &'a str>) -> Self {
+        let mut index = RouteIndex::default();
+
+        for (i, path) in paths.enumerate() {
+            index.insert(i, path);
+        }
+
+        index
+    }
+
+    // Adds the route at `position`, which must be past every indexed route.
+    pub(crate) fn insert(&mut self, position: usize, path: &str) {
+        if path.contains('{') {
+            self.parametric.push(position);
+        } else {
+            self.exact
+                .entry(normalize(path).into_owned())
+                .or_default()
+                .push(position);
+        }
+    }
+
+    // The positions of the static routes for `path`.
+    pub(crate) fn exact(&self, path: &str) -> &[usize] {
+        self.exact
+            .get(normalize(path).as_ref())
+            .map_or(&[][..], Vec::as_slice)
+    }
+
+    // The positions of every route with params.
+    pub(crate) fn parametric(&self) -> &[usize] {
+        &self.parametric
+    }
+
+    // The positions of routes that may match `path`, each with whether it
+    // still needs segment matching, merged back into registration order.
+    pub(crate) fn candidates(&self, path: &str) -> Candidates<'_> {
+        Candidates {
+            exact: self.exact(path),
+            parametric: &self.parametric,
+        }
+    }
+}
+
+pub(crate) struct Candidates<'a> {
+    exact: &'a [usize],
+    parametric: &'a [usize],
+}
+
+impl Iterator for Candidates<'_> {
+    type Item = (usize, bool);
+
+    // merges the two sorted position lists
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.exact.first(), self.parametric.first()) {
+            (Some(&e), Some(&p)) if e < p => {
+                self.exact = &self.exact[1..];
+                Some((e, false))
+            }
+            (_, Some(&p)) => {
+                self.parametric = &self.parametric[1..];
+                Some((p, true))
+            }
+            (Some(&e), None) => {
+                self.exact = &self.exact[1..];
+                Some((e, false))
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+// Reduces a path to its non-empty segments, the way segment matching sees it,
+// so `/a//b/` and `/a/b` share a key. Borrows when there's nothing to drop.
+fn normalize(path: &str) -> Cow<'_, str> {
+    if path == "/" || (path.starts_with('/') && !path.ends_with('/') && !path.contains("//")) {
+        return Cow::Borrowed(path);
+    }
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    Cow::Owned(format!("/{}", segments.join("/")))
+}
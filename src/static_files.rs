@@ -0,0 +1,366 @@
+use crate::{
+    Body, Response,
+    util::{
+        collapse_slashes, content_type, escape_html, percent_decode, percent_encode, with_query,
+    },
+};
+use hyper::{HeaderMap, header};
+use std::{
+    io::SeekFrom,
+    path::{Component, Path, PathBuf},
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+const INDEX_FILE: &str = "index.html";
+
+#[derive(Clone)]
+pub struct StaticFiles {
+    prefix: String,
+    dir: PathBuf,
+    autoindex: bool,
+    cache_control: Vec<(String, String)>,
+}
+
+impl StaticFiles {
+    pub(crate) fn new(prefix: &str, dir: PathBuf) -> Self {
+        StaticFiles {
+            prefix: String::from(prefix.trim_end_matches('/')),
+            dir,
+            autoindex: false,
+            cache_control: vec![],
+        }
+    }
+
+    /// Lists the contents of directories that have no `index.html`. Off by
+    /// default, as it exposes every file name in the directory.
+    pub fn autoindex(&mut self, enabled: bool) -> &mut Self {
+        self.autoindex = enabled;
+        self
+    }
+
+    /// Sends `Cache-Control: value` with files whose name matches `pattern`,
+    /// a glob where `*` matches any run of characters and `?` a single one,
+    /// e.g. `*.html` or `*.*.js` for content-hashed bundles. Rules are tried
+    /// in the order they're added and the first match wins.
+    pub fn cache_control(&mut self, pattern: &str, value: &str) -> &mut Self {
+        self.cache_control
+            .push((String::from(pattern), String::from(value)));
+        self
+    }
+
+    // The mount as a route pattern, for reporting which route served a request.
+    pub(crate) fn pattern(&self) -> String {
+        format!("{}/{{*path}}", self.prefix)
+    }
+
+    // Returns the part of the request path below the mount prefix, or `None`
+    // if the path isn't under this mount at all.
+    fn relative_path<'a>(&self, request_path: &'a str) -> Option<&'a str> {
+        let rest = request_path.strip_prefix(&self.prefix)?;
+
+        if rest.is_empty() || rest.starts_with('/') {
+            Some(rest.trim_start_matches('/'))
+        } else {
+            None
+        }
+    }
+
+    // Maps the relative request path onto the directory, refusing anything
+    // that could step outside of it.
+    fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        let mut path = self.dir.clone();
+
+        for segment in relative.split('/').filter(|s| !s.is_empty()) {
+            let segment = percent_decode(segment)?;
+
+            if segment.contains(['/', '\\', '\0']) {
+                return None;
+            }
+
+            match Path::new(&segment).components().next() {
+                Some(Component::Normal(_)) => path.push(&segment),
+                _ => return None,
+            }
+        }
+
+        Some(path)
+    }
+
+    // Canonicalizes `path` and checks it's still inside the directory once
+    // symlinks are followed, so a link can't point the mount elsewhere.
+    async fn contain(&self, path: &Path) -> Option<PathBuf> {
+        let dir = tokio::fs::canonicalize(&self.dir).await.ok()?;
+        let path = tokio::fs::canonicalize(path).await.ok()?;
+
+        path.starts_with(&dir).then_some(path)
+    }
+
+    // Serves the file the request path points to, or `None` if there isn't
+    // one, so the caller can fall through to its not found handling.
+    pub(crate) async fn serve(&self, uri: &hyper::Uri, headers: &HeaderMap) -> Option<Response> {
+        let request_path = uri.path();
+        let relative = self.relative_path(request_path)?;
+        let mut path = self.contain(&self.resolve(relative)?).await?;
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+
+        if metadata.is_dir() {
+            // relative links in the index only work with a trailing slash
+            if !request_path.ends_with('/') {
+                let location = format!("{}/", collapse_slashes(request_path));
+
+                return Some(Response::permanent_redirect(with_query(
+                    location,
+                    uri.query(),
+                )));
+            }
+
+            path.push(INDEX_FILE);
+
+            if self.autoindex && !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+                path.pop();
+
+                return self.list_directory(&path, request_path).await;
+            }
+        }
+
+        let path = self.contain(&path).await?;
+        let mut file = tokio::fs::File::open(&path).await.ok()?;
+        let metadata = file.metadata().await.ok()?;
+
+        if !metadata.is_file() {
+            return None;
+        }
+
+        let len = metadata.len();
+        let mut response = Response::html("")
+            .with_header("Content-Type", content_type(&path))
+            .with_header("Accept-Ranges", "bytes");
+
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+
+        if let Some((_, value)) = self
+            .cache_control
+            .iter()
+            .find(|(pattern, _)| glob_matches(pattern, name))
+        {
+            response = response.with_header("Cache-Control", value);
+        }
+
+        if let Ok(modified) = metadata.modified() {
+            response = response.with_header("Last-Modified", httpdate::fmt_http_date(modified));
+        }
+
+        let range = headers
+            .get(header::RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_range(value, len));
+
+        // the body is streamed rather than read up front, which also means
+        // HEAD requests never read the file as hyper doesn't poll the body
+        match range {
+            None => Some(
+                response
+                    .with_header("Content-Length", len.to_string())
+                    .with_body(Body::stream(ReaderStream::new(file))),
+            ),
+            Some(Ok((start, end))) => {
+                file.seek(SeekFrom::Start(start)).await.ok()?;
+
+                let part = file.take(end - start + 1);
+
+                Some(
+                    response
+                        .with_status(206)
+                        .with_header("Content-Range", format!("bytes {}-{}/{}", start, end, len))
+                        .with_header("Content-Length", (end - start + 1).to_string())
+                        .with_body(Body::stream(ReaderStream::new(part))),
+                )
+            }
+            Some(Err(())) => Some(
+                response
+                    .with_status(416)
+                    .with_header("Content-Range", format!("bytes */{}", len))
+                    .with_body(Body::empty()),
+            ),
+        }
+    }
+
+    async fn list_directory(&self, dir: &Path, request_path: &str) -> Option<Response> {
+        let mut entries = tokio::fs::read_dir(dir).await.ok()?;
+        let mut names = vec![];
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            let is_dir = entry.file_type().await.is_ok_and(|t| t.is_dir());
+
+            names.push((name, is_dir));
+        }
+
+        // directories first, then by name
+        names.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let title = escape_html(&percent_decode(request_path).unwrap_or_default());
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\n<body>\n<h1>Index of {title}</h1>\n<ul>\n"
+        );
+
+        if self
+            .relative_path(request_path)
+            .is_some_and(|p| !p.is_empty())
+        {
+            html.push_str("<li><a href=\"../\">../</a></li>\n");
+        }
+
+        for (name, is_dir) in names {
+            let slash = if is_dir { "/" } else { "" };
+
+            html.push_str(&format!(
+                "<li><a href=\"{href}{slash}\">{name}{slash}</a></li>\n",
+                href = percent_encode(&name),
+                name = escape_html(&name),
+            ));
+        }
+
+        html.push_str("</ul>\n</body>\n</html>\n");
+
+        Some(Response::html(html))
+    }
+}
+
+// Matches `name` against a glob of literal characters, `*` and `?`.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+            }
+            Some(c) if *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            // let the last `*` swallow one more character and retry
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// Parses a single `bytes=` range into inclusive start and end offsets.
+// `None` means the header should be ignored (unknown unit, multiple ranges or
+// bad syntax) and the whole file served, `Some(Err)` that the range can't be
+// satisfied for a file of `len` bytes.
+fn parse_range(value: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+
+    if spec.contains(',') {
+        return None;
+    }
+
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // suffix range, the last `end` bytes
+        let suffix = end.parse::<u64>().ok()?;
+
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let start = start.parse::<u64>().ok()?;
+        let end = match end {
+            "" => u64::MAX,
+            end => end.parse::<u64>().ok()?,
+        };
+
+        if end < start {
+            return None;
+        }
+
+        if start >= len {
+            return Some(Err(()));
+        }
+
+        (start, end.min(len - 1))
+    };
+
+    Some(Ok(range))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok((0, 99))));
+        assert_eq!(parse_range("bytes=900-2000", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=500-", 1000), Some(Ok((500, 999))));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=-5000", 1000), Some(Ok((0, 999))));
+    }
+
+    #[test]
+    fn refuses_ranges_past_the_end() {
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=1500-2000", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=-10", 0), Some(Err(())));
+    }
+
+    #[test]
+    fn ignores_ranges_it_does_not_serve() {
+        assert_eq!(parse_range("bytes=0-9,20-29", 1000), None);
+        assert_eq!(parse_range("items=0-9", 1000), None);
+        assert_eq!(parse_range("bytes=9-0", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_range("bytes=10", 1000), None);
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_matches("*.html", "index.html"));
+        assert!(glob_matches("*.html", ".html"));
+        assert!(glob_matches("*.*.js", "app.3f2a1c.js"));
+        assert!(glob_matches("*.*.js", "a.b.c.js"));
+        assert!(glob_matches("v?.css", "v2.css"));
+        assert!(glob_matches("fonts/*", "fonts/inter.woff2"));
+        assert!(glob_matches("*", ""));
+    }
+
+    #[test]
+    fn tells_globs_that_do_not_match() {
+        assert!(!glob_matches("*.html", "index.htm"));
+        assert!(!glob_matches("*.*.js", "app.js"));
+        assert!(!glob_matches("v?.css", "v10.css"));
+        assert!(!glob_matches("fonts/*", "images/logo.png"));
+        assert!(!glob_matches("index.html", "index.html.bak"));
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+};
+
+/// Why the certificate and key given to `Bobby::with_tls` couldn't be used.
+pub enum TlsError {
+    /// A file couldn't be read, with its path.
+    Read(String, std::io::Error),
+    /// The certificate chain is malformed or empty.
+    Certificate(String),
+    /// The private key is malformed or missing.
+    PrivateKey(String),
+    /// The certificate and key don't go together, or use something rustls
+    /// doesn't support.
+    Config(String),
+}
+
+impl std::fmt::Display for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlsError::Read(path, err) => write!(f, "Could not read \"{}\": {}", path, err),
+            TlsError::Certificate(reason) => write!(f, "Invalid certificate: {}", reason),
+            TlsError::PrivateKey(reason) => write!(f, "Invalid private key: {}", reason),
+            TlsError::Config(reason) => write!(f, "Invalid TLS setup: {}", reason),
+        }
+    }
+}
+
+impl std::fmt::Debug for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+// Builds the acceptor from a PEM certificate chain and private key, offering
+// HTTP/2 and HTTP/1.1 over ALPN so clients can pick either.
+pub(crate) fn acceptor(cert: &[u8], key: &[u8]) -> Result<TlsAcceptor, TlsError> {
+    let chain = CertificateDer::pem_slice_iter(cert)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| TlsError::Certificate(err.to_string()))?;
+
+    if chain.is_empty() {
+        return Err(TlsError::Certificate(String::from("no certificates found")));
+    }
+
+    let key =
+        PrivateKeyDer::from_pem_slice(key).map_err(|err| TlsError::PrivateKey(err.to_string()))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|err| TlsError::Config(err.to_string()))?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .map_err(|err| TlsError::Config(err.to_string()))?;
+
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
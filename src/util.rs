@@ -0,0 +1,168 @@
+use std::{
+    path::Path,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::{Instant, SystemTime},
+};
+
+// The current instant, when there's a clock to read. Both `Instant::now`
+// and `SystemTime::now` panic on wasm32-unknown-unknown, so only the server,
+// which never runs there, reads them, and without it nothing is timed.
+pub(crate) fn now() -> Option<Instant> {
+    #[cfg(feature = "server")]
+    let now = Some(Instant::now());
+    #[cfg(not(feature = "server"))]
+    let now = None;
+
+    now
+}
+
+// The current time, under the same terms as `now`.
+pub(crate) fn system_now() -> Option<SystemTime> {
+    #[cfg(feature = "server")]
+    let now = Some(SystemTime::now());
+    #[cfg(not(feature = "server"))]
+    let now = None;
+
+    now
+}
+
+// Decodes `%XX` sequences in a URI component. Returns `None` when a sequence
+// is malformed or the decoded bytes aren't valid UTF-8.
+pub(crate) fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = hex_byte(input.get(i + 1..i + 3)?)?;
+
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+// The byte two hex digits stand for. Checked by hand, as `from_str_radix`
+// would also take a sign, decoding `%+1` as `0x01`.
+pub(crate) fn hex_byte(hex: &str) -> Option<u8> {
+    if hex.len() != 2 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u8::from_str_radix(hex, 16).ok()
+}
+
+// Encodes everything but unreserved characters, for putting arbitrary text
+// into a single URI path segment.
+pub(crate) fn percent_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}
+
+// `path` with its empty segments dropped, for building a redirect `Location`
+// out of a request path without a leading `//host` turning it into a link to
+// another site.
+pub(crate) fn collapse_slashes(path: &str) -> String {
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    format!("/{}", segments.join("/"))
+}
+
+// Puts the request's query, if there was one, back onto a redirect path.
+pub(crate) fn with_query(path: String, query: Option<&str>) -> String {
+    match query {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    }
+}
+
+#[cfg(feature = "fs")]
+pub(crate) fn escape_html(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// Guesses the media type from the file extension.
+pub(crate) fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("wasm") => "application/wasm",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+// Wakes the thread `block_on` parked.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// Polls `future` to completion on the current thread, parking it in between,
+// for driving a request without an async runtime.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use bobby::{AccessLogFormat, Bobby, Response};
+use hyper::{HeaderMap, Method};
+use log::{Log, Metadata, Record};
+use std::sync::Mutex;
+
+// Keeps the access log lines, to check what got logged.
+struct Lines(Mutex<Vec<String>>);
+
+impl Log for Lines {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "bobby::access"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LINES: Lines = Lines(Mutex::new(vec![]));
+
+#[test]
+fn logs_json_lines_with_no_bytes_for_head() {
+    log::set_logger(&LINES).unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+
+    let mut app = Bobby::new();
+
+    app.with_request_log(false)
+        .with_access_log(AccessLogFormat::Json);
+    app.get("/users/{id}", |_| Response::html("A user, id 5."));
+
+    app.handle(Method::GET, "/users/5", HeaderMap::new(), "");
+    app.handle(Method::HEAD, "/users/5", HeaderMap::new(), "");
+    app.handle(Method::GET, "/missing", HeaderMap::new(), "");
+
+    let lines = LINES.0.lock().unwrap();
+    let without_duration: Vec<String> = lines
+        .iter()
+        .map(|line| {
+            let start = line.find(",\"duration_ms\":").unwrap();
+            let end = line.find(",\"client_ip\":").unwrap();
+
+            format!("{}{}", &line[..start], &line[end..])
+        })
+        .collect();
+
+    assert_eq!(
+        without_duration,
+        [
+            r#"{"method":"GET","path":"/users/5","matched_route":"/users/{id}","status":200,"client_ip":null,"bytes":13}"#,
+            r#"{"method":"HEAD","path":"/users/5","matched_route":"/users/{id}","status":200,"client_ip":null,"bytes":0}"#,
+            r#"{"method":"GET","path":"/missing","matched_route":null,"status":404,"client_ip":null,"bytes":10}"#,
+        ]
+    );
+}
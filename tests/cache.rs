@@ -0,0 +1,143 @@
+use bobby::{Bobby, Response};
+use hyper::{HeaderMap, Method};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::Duration;
+
+// An app whose `/{page}` route is cached for `ttl` and answers with its call
+// count, so a cached answer shows the count of an earlier call.
+fn counting_app(ttl: Duration, respond: fn(Response) -> Response) -> Bobby {
+    let mut app = Bobby::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    app.get("/{page}", move |req| {
+        let n = calls.fetch_add(1, Ordering::SeqCst);
+
+        respond(Response::text(format!(
+            "{} {n}",
+            req.param("page").unwrap()
+        )))
+    })
+    .cache(ttl);
+
+    app
+}
+
+fn get(app: &Bobby, uri: &str, headers: &[(&'static str, &str)]) -> String {
+    let mut map = HeaderMap::new();
+
+    for (name, value) in headers {
+        map.insert(*name, value.parse().unwrap());
+    }
+
+    let response = app.handle(Method::GET, uri, map, "");
+
+    String::from_utf8_lossy(response.body().as_bytes().unwrap_or_default()).into_owned()
+}
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+#[test]
+fn answers_repeat_requests_from_the_cache() {
+    let app = counting_app(MINUTE, |response| response);
+
+    assert_eq!(get(&app, "/a", &[]), "a 0");
+    assert_eq!(get(&app, "/a", &[]), "a 0");
+    assert_eq!(get(&app, "/a?page=2", &[]), "a 1");
+}
+
+#[test]
+fn calls_the_handler_again_once_the_ttl_is_up() {
+    let app = counting_app(Duration::from_millis(50), |response| response);
+
+    assert_eq!(get(&app, "/a", &[]), "a 0");
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(get(&app, "/a", &[]), "a 1");
+}
+
+#[test]
+fn keeps_responses_apart_by_the_headers_they_vary_on() {
+    let app = counting_app(MINUTE, |response| {
+        response.with_header("Vary", "Accept-Language")
+    });
+
+    assert_eq!(get(&app, "/a", &[("accept-language", "en")]), "a 0");
+    assert_eq!(get(&app, "/a", &[("accept-language", "et")]), "a 1");
+    assert_eq!(get(&app, "/a", &[("accept-language", "et")]), "a 1");
+}
+
+#[test]
+fn never_shares_responses_to_requests_with_credentials() {
+    let app = counting_app(MINUTE, |response| response);
+
+    assert_eq!(get(&app, "/a", &[("authorization", "Bearer alice")]), "a 0");
+    assert_eq!(get(&app, "/a", &[("authorization", "Bearer bob")]), "a 1");
+    assert_eq!(get(&app, "/a", &[]), "a 2");
+    assert_eq!(get(&app, "/a", &[("authorization", "Bearer bob")]), "a 3");
+}
+
+#[test]
+fn only_shares_responses_to_requests_with_cookies_when_they_vary_on_them() {
+    let app = counting_app(MINUTE, |response| response);
+
+    assert_eq!(get(&app, "/a", &[("cookie", "user=alice")]), "a 0");
+    assert_eq!(get(&app, "/a", &[("cookie", "user=bob")]), "a 1");
+    assert_eq!(get(&app, "/a", &[]), "a 2");
+    assert_eq!(get(&app, "/a", &[("cookie", "user=bob")]), "a 3");
+
+    let app = counting_app(MINUTE, |response| response.with_header("Vary", "Cookie"));
+
+    assert_eq!(get(&app, "/a", &[("cookie", "user=alice")]), "a 0");
+    assert_eq!(get(&app, "/a", &[("cookie", "user=alice")]), "a 0");
+    assert_eq!(get(&app, "/a", &[("cookie", "user=bob")]), "a 1");
+}
+
+#[test]
+fn leaves_out_responses_marked_no_store_or_private() {
+    for cache_control in ["no-store", "max-age=60, private"] {
+        let mut app = Bobby::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        app.get("/a", move |_| {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+
+            Response::text(n.to_string()).with_header("Cache-Control", cache_control)
+        })
+        .cache(MINUTE);
+
+        assert_eq!(get(&app, "/a", &[]), "0");
+        assert_eq!(get(&app, "/a", &[]), "1");
+    }
+}
+
+#[test]
+fn evicts_the_least_recently_used_response_first() {
+    let mut app = counting_app(MINUTE, |response| response);
+
+    app.with_cache_capacity(2);
+
+    assert_eq!(get(&app, "/a", &[]), "a 0");
+    assert_eq!(get(&app, "/b", &[]), "b 1");
+    assert_eq!(get(&app, "/a", &[]), "a 0");
+    assert_eq!(get(&app, "/c", &[]), "c 2");
+
+    assert_eq!(get(&app, "/a", &[]), "a 0");
+    assert_eq!(get(&app, "/b", &[]), "b 3");
+}
+
+#[test]
+fn evicts_down_to_a_lowered_capacity() {
+    let mut app = counting_app(MINUTE, |response| response);
+
+    assert_eq!(get(&app, "/a", &[]), "a 0");
+    assert_eq!(get(&app, "/b", &[]), "b 1");
+    assert_eq!(get(&app, "/c", &[]), "c 2");
+
+    app.with_cache_capacity(1);
+
+    assert_eq!(get(&app, "/c", &[]), "c 2");
+    assert_eq!(get(&app, "/a", &[]), "a 3");
+    assert_eq!(get(&app, "/b", &[]), "b 4");
+}
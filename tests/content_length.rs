@@ -0,0 +1,42 @@
+use bobby::{Bobby, Response};
+use hyper::{HeaderMap, Method};
+
+fn app() -> Bobby {
+    let mut app = Bobby::new();
+
+    app.get("/emoji", |_| Response::html("😀"));
+    app.get("/mixed", |_| Response::text("héllo 😀"));
+    app.get("/mismatched", |_| {
+        Response::html("Hello!").with_header("Content-Length", "2")
+    });
+
+    app
+}
+
+#[test]
+fn counts_bytes_not_characters() {
+    let app = app();
+
+    let response = app.handle(Method::GET, "/emoji", HeaderMap::new(), "");
+    assert_eq!(response.header("content-length"), Some("4"));
+
+    let response = app.handle(Method::GET, "/mixed", HeaderMap::new(), "");
+    assert_eq!(response.header("content-length"), Some("11"));
+    assert_eq!(response.body().as_bytes().map(<[u8]>::len), Some(11));
+}
+
+#[test]
+fn replaces_a_mismatched_content_length() {
+    let response = app().handle(Method::GET, "/mismatched", HeaderMap::new(), "");
+
+    assert_eq!(response.header("content-length"), Some("6"));
+    assert_eq!(response.body().as_bytes(), Some(&b"Hello!"[..]));
+}
+
+#[test]
+fn keeps_the_content_length_for_head() {
+    let response = app().handle(Method::HEAD, "/emoji", HeaderMap::new(), "");
+
+    assert_eq!(response.header("content-length"), Some("4"));
+    assert_eq!(response.body().as_bytes(), Some(&b""[..]));
+}
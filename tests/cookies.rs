@@ -0,0 +1,64 @@
+use bobby::{Bobby, Cookie, Response, SameSite};
+use hyper::{HeaderMap, Method, header};
+use std::time::Duration;
+
+#[test]
+fn reads_a_cookie_by_name() {
+    let mut app = Bobby::new();
+
+    app.get("/", |req| {
+        Response::text(format!(
+            "{:?} {:?}",
+            req.cookie("theme"),
+            req.cookie("lang")
+        ))
+    });
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert(header::COOKIE, "session=abc; theme=dark".parse().unwrap());
+
+    let response = app.handle(Method::GET, "/", headers, "");
+
+    assert_eq!(
+        response.body().as_bytes(),
+        Some(&b"Some(\"dark\") None"[..])
+    );
+}
+
+#[test]
+fn sends_a_set_cookie_header_per_cookie() {
+    let mut app = Bobby::new();
+
+    app.get("/", |_| {
+        Response::html("Hi")
+            .with_cookie(
+                Cookie::new("session", "abc")
+                    .path("/")
+                    .domain("example.com")
+                    .max_age(Duration::from_secs(3600))
+                    .http_only()
+                    .secure()
+                    .same_site(SameSite::Lax),
+            )
+            .with_cookie(Cookie::new("theme", "dark"))
+            .with_cookie(Cookie::new("bad", "two words"))
+            .with_cookie(Cookie::removal("old").path("/"))
+    });
+
+    let response = app.handle(Method::GET, "/", HeaderMap::new(), "");
+    let cookies: Vec<&str> = response
+        .headers()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+        .map(|(_, value)| value)
+        .collect();
+
+    assert_eq!(
+        cookies,
+        [
+            "session=abc; Path=/; Domain=example.com; Max-Age=3600; HttpOnly; Secure; SameSite=Lax",
+            "theme=dark",
+            "old=; Path=/; Max-Age=0",
+        ]
+    );
+}
@@ -0,0 +1,144 @@
+use bobby::{Bobby, Cors, Response};
+use hyper::{HeaderMap, Method};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+
+    for (name, value) in pairs {
+        map.insert(*name, value.parse().unwrap());
+    }
+
+    map
+}
+
+fn app(cors: Cors, calls: Arc<AtomicUsize>) -> Bobby {
+    let mut app = Bobby::new();
+
+    app.cors(cors);
+    app.get("/data", move |_| {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Response::text("data")
+    });
+
+    app
+}
+
+fn listed() -> Cors {
+    Cors::new()
+        .allow_origin("https://app.example.com/")
+        .allow_methods(["GET", "PUT"])
+        .allow_headers(["Content-Type"])
+        .expose_headers(["X-Total"])
+        .allow_credentials()
+        .max_age(std::time::Duration::from_secs(600))
+}
+
+#[test]
+fn answers_preflights_without_calling_the_handler() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let app = app(listed(), Arc::clone(&calls));
+
+    let response = app.handle(
+        Method::OPTIONS,
+        "/data",
+        headers(&[
+            ("origin", "https://app.example.com"),
+            ("access-control-request-method", "PUT"),
+        ]),
+        "",
+    );
+
+    assert_eq!(response.status(), 204);
+    assert_eq!(
+        response.header("Access-Control-Allow-Origin"),
+        Some("https://app.example.com")
+    );
+    assert_eq!(
+        response.header("Access-Control-Allow-Methods"),
+        Some("GET, PUT")
+    );
+    assert_eq!(
+        response.header("Access-Control-Allow-Headers"),
+        Some("Content-Type")
+    );
+    assert_eq!(
+        response.header("Access-Control-Allow-Credentials"),
+        Some("true")
+    );
+    assert_eq!(response.header("Access-Control-Max-Age"), Some("600"));
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn reflects_only_listed_origins() {
+    let app = app(listed(), Arc::new(AtomicUsize::new(0)));
+
+    let allowed = app.handle(
+        Method::GET,
+        "/data",
+        headers(&[("origin", "https://APP.example.com")]),
+        "",
+    );
+
+    assert_eq!(allowed.status(), 200);
+    assert_eq!(
+        allowed.header("Access-Control-Allow-Origin"),
+        Some("https://APP.example.com")
+    );
+    assert_eq!(
+        allowed.header("Access-Control-Expose-Headers"),
+        Some("X-Total")
+    );
+    assert_eq!(allowed.header("Vary"), Some("Origin"));
+
+    let refused = app.handle(
+        Method::GET,
+        "/data",
+        headers(&[("origin", "https://evil.example.com")]),
+        "",
+    );
+
+    assert_eq!(refused.status(), 200);
+    assert_eq!(refused.header("Access-Control-Allow-Origin"), None);
+    assert_eq!(refused.header("Access-Control-Allow-Credentials"), None);
+    assert_eq!(refused.header("Vary"), Some("Origin"));
+
+    let preflight = app.handle(
+        Method::OPTIONS,
+        "/data",
+        headers(&[
+            ("origin", "https://evil.example.com"),
+            ("access-control-request-method", "PUT"),
+        ]),
+        "",
+    );
+
+    assert_eq!(preflight.status(), 204);
+    assert_eq!(preflight.header("Access-Control-Allow-Origin"), None);
+    assert_eq!(preflight.header("Access-Control-Allow-Methods"), None);
+}
+
+#[test]
+fn sends_a_plain_star_for_any_origin() {
+    let app = app(Cors::new().allow_origin("*"), Arc::new(AtomicUsize::new(0)));
+
+    let response = app.handle(
+        Method::GET,
+        "/data",
+        headers(&[("origin", "https://anywhere.example.com")]),
+        "",
+    );
+
+    assert_eq!(response.header("Access-Control-Allow-Origin"), Some("*"));
+    assert_eq!(response.header("Vary"), None);
+}
+
+#[test]
+#[should_panic(expected = "Cannot allow credentials from any origin")]
+fn refuses_credentials_from_any_origin() {
+    Bobby::new().cors(Cors::new().allow_origin("*").allow_credentials());
+}
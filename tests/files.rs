@@ -0,0 +1,119 @@
+use bobby::{Bobby, Response, TestClient};
+use std::{path::PathBuf, time::Duration};
+
+fn write_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bobby-{}-{}", std::process::id(), name));
+
+    std::fs::write(&path, contents).unwrap();
+
+    path
+}
+
+#[test]
+fn streams_files_with_their_length() {
+    let path = write_file("hello.txt", "Hello, file");
+    let mut app = Bobby::new();
+
+    app.get("/file", move |_| Response::file(&path));
+
+    let client = TestClient::new(&app).unwrap();
+    let response = client.get("/file").unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(response.header("content-length"), Some("11"));
+    assert!(response.header("last-modified").is_some());
+    assert_eq!(response.text(), "Hello, file");
+}
+
+#[test]
+fn answers_a_missing_file_with_a_404_keeping_middleware_headers() {
+    let mut app = Bobby::new();
+
+    app.middleware(|req, next| next.run(req).with_header("X-Served-By", "bobby"));
+    app.get("/missing", |_| Response::file("/no/such/file.txt"));
+
+    let client = TestClient::new(&app).unwrap();
+    let response = client.get("/missing").unwrap();
+
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.header("content-type"), Some("text/html"));
+    assert_eq!(response.header("x-served-by"), Some("bobby"));
+    assert_eq!(response.text(), "Not found.");
+}
+
+#[test]
+fn leaves_files_out_of_the_response_cache() {
+    let path = write_file("cached.txt", "first");
+    let mut app = Bobby::new();
+
+    let served = path.clone();
+    app.get("/cached", move |_| Response::file(&served))
+        .cache(Duration::from_secs(60));
+
+    let client = TestClient::new(&app).unwrap();
+
+    assert_eq!(client.get("/cached").unwrap().text(), "first");
+
+    std::fs::write(&path, "second").unwrap();
+
+    assert_eq!(client.get("/cached").unwrap().text(), "second");
+}
+
+#[test]
+fn puts_the_group_prefix_in_front_of_static_files() {
+    let path = write_file("grouped.txt", "Grouped");
+    let dir = path.parent().unwrap().to_path_buf();
+    let name = path.file_name().unwrap().to_str().unwrap().to_string();
+    let mut app = Bobby::new();
+
+    app.group("/app", |group| {
+        group.static_files("/assets", &dir);
+    });
+
+    let client = TestClient::new(&app).unwrap();
+    let response = client.get(&format!("/app/assets/{}", name)).unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text(), "Grouped");
+}
+
+#[test]
+fn serves_a_directory_with_content_types_and_no_way_out() {
+    let public = std::env::temp_dir().join(format!("bobby-{}-public", std::process::id()));
+
+    std::fs::create_dir_all(&public).unwrap();
+    std::fs::write(public.join("style.css"), "body {}").unwrap();
+    std::fs::write(public.join("logo.svg"), "<svg/>").unwrap();
+    write_file("secret.txt", "Secret");
+
+    let mut app = Bobby::new();
+
+    app.static_files("/assets", &public);
+
+    let client = TestClient::new(&app).unwrap();
+    let css = client.get("/assets/style.css").unwrap();
+
+    assert_eq!(css.status(), 200);
+    assert_eq!(css.header("content-type"), Some("text/css; charset=utf-8"));
+    assert_eq!(css.header("content-length"), Some("7"));
+    assert_eq!(css.text(), "body {}");
+
+    let svg = client.get("/assets/logo.svg").unwrap();
+
+    assert_eq!(svg.header("content-type"), Some("image/svg+xml"));
+    assert_eq!(client.get("/assets/missing.css").unwrap().status(), 404);
+
+    let outside = format!("bobby-{}-secret.txt", std::process::id());
+
+    for uri in [
+        format!("/assets/../{}", outside),
+        format!("/assets/%2e%2e/{}", outside),
+        format!("/assets/..%2f{}", outside),
+    ] {
+        assert_eq!(client.get(&uri).unwrap().status(), 404, "{}", uri);
+    }
+}
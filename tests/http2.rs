@@ -0,0 +1,76 @@
+use bobby::{Bobby, Body, BodyWriter, Response, TestClient};
+use bytes::Bytes;
+use http_body_util::Empty;
+use hyper::{Request, client::conn::http2};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::net::TcpStream;
+
+// An app whose responses stay open until the test lets go of their writers,
+// so each request keeps its stream busy.
+fn holding_app(max_streams: Option<u32>) -> (Bobby, Arc<Mutex<Vec<BodyWriter>>>) {
+    let mut app = Bobby::new();
+    let writers = Arc::new(Mutex::new(vec![]));
+    let held = Arc::clone(&writers);
+
+    if let Some(max) = max_streams {
+        app.with_http2_max_concurrent_streams(max);
+    }
+
+    app.get("/hold", move |_| {
+        let (writer, body) = Body::channel();
+
+        held.lock().unwrap().push(writer);
+        Response::text("").with_body(body)
+    });
+
+    (app, writers)
+}
+
+// Whether a second request gets its response while the first one's stream
+// is still open, over a single HTTP/2 connection.
+fn second_stream_answered(addr: SocketAddr) -> bool {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    runtime.block_on(async move {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (mut sender, connection) = http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+            .await
+            .unwrap();
+
+        tokio::spawn(connection);
+
+        let request = || {
+            Request::get(format!("http://{}/hold", addr))
+                .body(Empty::<Bytes>::new())
+                .unwrap()
+        };
+
+        let first = sender.send_request(request()).await.unwrap();
+        let second =
+            tokio::time::timeout(Duration::from_millis(300), sender.send_request(request())).await;
+
+        drop(first);
+
+        second.is_ok()
+    })
+}
+
+#[test]
+fn caps_concurrent_http2_streams() {
+    let (app, writers) = holding_app(Some(1));
+    let client = TestClient::new(&app).unwrap();
+
+    assert!(!second_stream_answered(client.addr()));
+    writers.lock().unwrap().clear();
+
+    let (app, writers) = holding_app(None);
+    let client = TestClient::new(&app).unwrap();
+
+    assert!(second_stream_answered(client.addr()));
+    writers.lock().unwrap().clear();
+}
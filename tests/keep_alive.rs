@@ -0,0 +1,49 @@
+use bobby::{Bobby, Response, TestClient};
+use bytes::Bytes;
+use http_body_util::Full;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn serves_several_requests_over_one_connection() {
+    let mut app = Bobby::new();
+
+    // requests served over the last connection that closed, `u64::MAX` until
+    // one has
+    let served = Arc::new(AtomicU64::new(u64::MAX));
+    let reported = Arc::clone(&served);
+
+    app.get("/", |_| Response::html("Hello"));
+    app.on_connection_close(move |_peer, requests, _duration| {
+        reported.store(requests, Ordering::SeqCst);
+    });
+
+    let client = TestClient::new(&app).unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(client.get("/").unwrap().status(), 200);
+    }
+
+    // the last request asks the server to close, so the count gets reported
+    let request = hyper::Request::builder()
+        .uri("/")
+        .header("Connection", "close")
+        .body(Full::new(Bytes::new()))
+        .unwrap();
+
+    assert_eq!(client.send(request).unwrap().status(), 200);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    while served.load(Ordering::SeqCst) == u64::MAX && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(served.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,37 @@
+use bobby::{Bobby, Response};
+use hyper::{HeaderMap, Method};
+
+fn status(app: &Bobby, uri: &str) -> u16 {
+    app.handle(Method::GET, uri, HeaderMap::new(), "").status()
+}
+
+#[test]
+fn matches_allowed_paths_like_routes() {
+    let mut app = Bobby::new();
+
+    app.get("/health", |_| Response::text("OK"));
+    app.get("/admin/{rest:*}", |_| Response::text("Admin"));
+    app.get("/shop", |_| Response::text("Shop"));
+    app.allow_during_maintenance("/health/");
+    app.allow_during_maintenance("/admin/{rest:*}");
+    app.set_maintenance(true);
+
+    assert_eq!(status(&app, "/health"), 200);
+    assert_eq!(status(&app, "/health/"), 200);
+    assert_eq!(status(&app, "/admin"), 200);
+    assert_eq!(status(&app, "/admin/users/5"), 200);
+    assert_eq!(status(&app, "/shop"), 503);
+}
+
+#[test]
+fn puts_the_group_prefix_in_front_of_allowed_paths() {
+    let mut app = Bobby::new();
+
+    app.group("/api", |api| {
+        api.get("/health", |_| Response::text("OK"));
+        api.allow_during_maintenance("/health");
+    });
+    app.set_maintenance(true);
+
+    assert_eq!(status(&app, "/api/health"), 200);
+}
@@ -0,0 +1,198 @@
+use bobby::{Bobby, Response};
+use hyper::{HeaderMap, Method, header};
+
+fn text(response: &Response) -> String {
+    String::from_utf8_lossy(response.body().as_bytes().unwrap_or_default()).into_owned()
+}
+
+#[test]
+fn reads_cookies_from_every_cookie_header() {
+    let mut app = Bobby::new();
+
+    app.get("/", |req| {
+        let mut cookies: Vec<String> = req
+            .cookies()
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+
+        cookies.sort();
+
+        Response::text(cookies.join(" "))
+    });
+
+    let mut headers = HeaderMap::new();
+
+    headers.append(header::COOKIE, "a=1; b=2".parse().unwrap());
+    headers.append(header::COOKIE, "c=3".parse().unwrap());
+
+    let response = app.handle(Method::GET, "/", headers, "");
+
+    assert_eq!(text(&response), "a=1 b=2 c=3");
+}
+
+fn query_app(strict: bool) -> Bobby {
+    let mut app = Bobby::new();
+
+    app.with_strict_query(strict);
+    app.get("/", |req| {
+        Response::text(format!("{:?} {:?}", req.query("a"), req.query("b")))
+    });
+
+    app
+}
+
+#[test]
+fn leaves_malformed_percent_sequences_literal() {
+    let app = query_app(false);
+
+    let response = app.handle(Method::GET, "/?a=%zz&b=%41", HeaderMap::new(), "");
+    assert_eq!(response.status(), 200);
+    assert_eq!(text(&response), "Some(\"%zz\") Some(\"A\")");
+
+    let response = app.handle(Method::GET, "/?a=%a&b=%-1", HeaderMap::new(), "");
+    assert_eq!(text(&response), "Some(\"%a\") Some(\"%-1\")");
+}
+
+#[test]
+fn rejects_malformed_percent_sequences_when_strict() {
+    let app = query_app(true);
+
+    for uri in ["/?a=%zz", "/?a=%a", "/?a=%-1"] {
+        assert_eq!(
+            app.handle(Method::GET, uri, HeaderMap::new(), "").status(),
+            400
+        );
+    }
+
+    let response = app.handle(Method::GET, "/?a=%41", HeaderMap::new(), "");
+    assert_eq!(text(&response), "Some(\"A\") None");
+}
+
+#[test]
+fn runs_before_route_closures_that_capture_state() {
+    #[derive(Clone)]
+    struct Tenant(String);
+
+    let mut app = Bobby::new();
+    let tenant = String::from("acme");
+
+    app.before_route(move |req| {
+        req.extensions_mut().insert(Tenant(tenant.clone()));
+    });
+    app.get("/", |req| {
+        let tenant = req
+            .extensions()
+            .get::<Tenant>()
+            .map(|tenant| tenant.0.clone());
+
+        Response::text(tenant.unwrap_or_default())
+    });
+
+    let response = app.handle(Method::GET, "/", HeaderMap::new(), "");
+
+    assert_eq!(text(&response), "acme");
+}
+
+fn override_app(enabled: bool) -> Bobby {
+    let mut app = Bobby::new();
+
+    app.with_method_override(enabled);
+    app.post("/posts/{id}", |_| Response::text("post"));
+    app.put("/posts/{id}", |_| Response::text("put"));
+    app.delete("/posts/{id}", |req| {
+        Response::text(format!("delete {}", req.method()))
+    });
+
+    app
+}
+
+fn post(app: &Bobby, pairs: &[(&'static str, &str)], body: &'static str) -> String {
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in pairs {
+        headers.insert(*name, value.parse().unwrap());
+    }
+
+    text(&app.handle(Method::POST, "/posts/1", headers, body))
+}
+
+const FORM: (&str, &str) = ("content-type", "application/x-www-form-urlencoded");
+
+#[test]
+fn overrides_the_method_of_posts_when_enabled() {
+    let app = override_app(true);
+
+    assert_eq!(
+        post(&app, &[FORM], "_method=delete&title=x"),
+        "delete DELETE"
+    );
+    assert_eq!(post(&app, &[("x-http-method-override", "PUT")], ""), "put");
+    assert_eq!(
+        post(
+            &app,
+            &[FORM, ("x-http-method-override", "DELETE")],
+            "_method=PUT"
+        ),
+        "delete DELETE"
+    );
+
+    // only PUT, PATCH and DELETE can be asked for
+    assert_eq!(post(&app, &[FORM], "_method=GET"), "post");
+    assert_eq!(
+        post(&app, &[("x-http-method-override", "nonsense")], ""),
+        "post"
+    );
+}
+
+#[test]
+fn leaves_the_method_alone_by_default() {
+    let app = override_app(false);
+
+    assert_eq!(post(&app, &[FORM], "_method=DELETE"), "post");
+    assert_eq!(post(&app, &[("x-http-method-override", "PUT")], ""), "post");
+}
+
+fn urls(trusted: bool, pairs: &[(&'static str, &str)]) -> String {
+    let mut app = Bobby::new();
+
+    app.with_trusted_proxy(trusted);
+    app.get("/", |req| {
+        Response::text(format!("{} {}", req.base_url(), req.absolute_url("/login")))
+    });
+
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in pairs {
+        headers.insert(*name, value.parse().unwrap());
+    }
+
+    text(&app.handle(Method::GET, "/", headers, ""))
+}
+
+#[test]
+fn builds_absolute_urls_from_the_host() {
+    assert_eq!(
+        urls(false, &[("host", "example.com:8080")]),
+        "http://example.com:8080 http://example.com:8080/login"
+    );
+    assert_eq!(urls(false, &[]), "http://localhost http://localhost/login");
+}
+
+#[test]
+fn builds_absolute_urls_from_forwarded_headers_only_behind_a_trusted_proxy() {
+    let forwarded = [
+        ("host", "10.0.0.5"),
+        ("x-forwarded-proto", "https, http"),
+        ("x-forwarded-host", "example.com"),
+    ];
+
+    assert_eq!(
+        urls(true, &forwarded),
+        "https://example.com https://example.com/login"
+    );
+    assert_eq!(
+        urls(false, &forwarded),
+        "http://10.0.0.5 http://10.0.0.5/login"
+    );
+}
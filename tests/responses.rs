@@ -0,0 +1,170 @@
+use bobby::{Bobby, HttpError, Response};
+use hyper::{HeaderMap, Method};
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::Duration;
+
+#[test]
+fn drops_the_body_of_no_body_statuses() {
+    let mut app = Bobby::new();
+
+    app.get("/no-content", |_| Response::html("Gone").with_status(204));
+    app.get("/not-modified", |_| Response::html("Same").with_status(304));
+
+    for (uri, status) in [("/no-content", 204), ("/not-modified", 304)] {
+        let response = app.handle(Method::GET, uri, HeaderMap::new(), "");
+
+        assert_eq!(response.status(), status);
+        assert_eq!(response.body().as_bytes(), Some(&b""[..]));
+        assert_eq!(response.header("content-length"), None);
+    }
+}
+
+fn with_layout() -> Bobby {
+    let mut app = Bobby::new();
+
+    let title = String::from("Site");
+
+    app.with_layout(move |content| {
+        format!(
+            "<html><title>{}</title><body>{}</body></html>",
+            title, content
+        )
+    });
+    app.get("/page", |_| Response::html("Page"));
+    app.get("/moved", |_| Response::redirect("/page"));
+    app.get("/missing", |_| -> Result<Response, HttpError> {
+        Err(HttpError::new(404, "No such page."))
+    });
+
+    app
+}
+
+#[test]
+fn leaves_redirects_out_of_the_layout() {
+    let app = with_layout();
+
+    let response = app.handle(Method::GET, "/page", HeaderMap::new(), "");
+    assert_eq!(
+        response.body().as_bytes(),
+        Some(&b"<html><title>Site</title><body>Page</body></html>"[..])
+    );
+
+    let response = app.handle(Method::GET, "/moved", HeaderMap::new(), "");
+    assert_eq!(response.status(), 302);
+    assert_eq!(response.body().as_bytes(), Some(&b""[..]));
+}
+
+#[test]
+fn leaves_errors_out_of_the_layout() {
+    let response = with_layout().handle(Method::GET, "/missing", HeaderMap::new(), "");
+
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.body().as_bytes(), Some(&b"No such page."[..]));
+}
+
+#[test]
+fn reports_timings_to_a_closure() {
+    let mut app = Bobby::new();
+    let timings = Arc::new(Mutex::new(vec![]));
+
+    let recorded = Arc::clone(&timings);
+    app.on_request_timing(move |matched_path, method, status, _duration| {
+        let entry = format!("{} {:?} {}", method, matched_path, status);
+
+        recorded.lock().unwrap().push(entry);
+    });
+    app.get("/users/{id}", |_| Response::html("User"));
+
+    app.handle(Method::GET, "/users/5", HeaderMap::new(), "");
+    app.handle(Method::GET, "/nope", HeaderMap::new(), "");
+
+    assert_eq!(
+        *timings.lock().unwrap(),
+        ["GET Some(\"/users/{id}\") 200", "GET None 404"]
+    );
+}
+
+#[test]
+fn leaves_set_cookie_headers_out_of_the_response_cache() {
+    let mut app = Bobby::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+
+    app.get("/session", move |_| {
+        let n = counter.fetch_add(1, Ordering::SeqCst);
+
+        Response::html("Hi").with_header("set-cookie", format!("session={n}"))
+    })
+    .cache(Duration::from_secs(60));
+
+    for _ in 0..2 {
+        app.handle(Method::GET, "/session", HeaderMap::new(), "");
+    }
+
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn answers_head_without_a_body() {
+    let mut app = Bobby::new();
+
+    app.get("/page", |_| Response::html("The page"));
+    app.get("/report", |_| Response::html("The full report"));
+    app.head("/report", |_| {
+        Response::html("Ignored").with_header("X-Report", "head")
+    });
+    app.post("/form", |_| Response::html("Posted"));
+
+    let page = app.handle(Method::HEAD, "/page", HeaderMap::new(), "");
+
+    assert_eq!(page.status(), 200);
+    assert_eq!(page.header("content-length"), Some("8"));
+    assert_eq!(page.header("content-type"), Some("text/html"));
+    assert_eq!(page.body().as_bytes(), Some(&b""[..]));
+
+    let report = app.handle(Method::HEAD, "/report", HeaderMap::new(), "");
+
+    assert_eq!(report.header("x-report"), Some("head"));
+    assert_eq!(report.header("content-length"), Some("7"));
+    assert_eq!(report.body().as_bytes(), Some(&b""[..]));
+
+    let form = app.handle(Method::HEAD, "/form", HeaderMap::new(), "");
+
+    assert_eq!(form.status(), 405);
+    assert_eq!(form.body().as_bytes(), Some(&b""[..]));
+}
+
+#[test]
+fn lets_fallback_handlers_return_results() {
+    let mut app = Bobby::new();
+
+    app.get("/only-get", |_| Response::html("Got"));
+    app.not_found(|req| -> Result<Response, HttpError> {
+        let id = req.header("X-Request-Id").ok_or(HttpError::status(400))?;
+
+        Ok(Response::text(format!("No route for {}", id)).with_status(404))
+    });
+    app.method_not_allowed(|_| Err::<Response, _>(HttpError::new(405, "Nope")));
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("x-request-id", "abc".parse().unwrap());
+
+    let response = app.handle(Method::GET, "/missing", headers, "");
+
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.body().as_bytes(), Some(&b"No route for abc"[..]));
+    assert_eq!(
+        app.handle(Method::GET, "/missing", HeaderMap::new(), "")
+            .status(),
+        400
+    );
+
+    let response = app.handle(Method::POST, "/only-get", HeaderMap::new(), "");
+
+    assert_eq!(response.status(), 405);
+    assert_eq!(response.header("allow"), Some("GET, HEAD"));
+}
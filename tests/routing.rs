@@ -0,0 +1,470 @@
+use bobby::{Bobby, Resource, Response, RouteConflict, RouteError, TrailingSlash};
+use hyper::{HeaderMap, Method};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+fn get(app: &Bobby, uri: &str) -> (u16, String) {
+    let response = app.handle(Method::GET, uri, HeaderMap::new(), "");
+    let body = response.body().as_bytes().unwrap_or_default();
+
+    (
+        response.status(),
+        String::from_utf8_lossy(body).into_owned(),
+    )
+}
+
+#[test]
+fn tells_a_missing_rest_from_an_empty_one() {
+    let mut app = Bobby::new();
+
+    app.get("/files/{path:*}", |req| {
+        Response::text(format!("{:?}", req.param("path")))
+    });
+    app.get("/docs/{path:+}", |req| {
+        Response::text(format!("{:?}", req.param("path")))
+    });
+
+    assert_eq!(get(&app, "/files"), (200, String::from("None")));
+    assert_eq!(get(&app, "/files/"), (200, String::from("Some(\"\")")));
+    assert_eq!(
+        get(&app, "/files/a/b"),
+        (200, String::from("Some(\"a/b\")"))
+    );
+
+    assert_eq!(get(&app, "/docs").0, 404);
+    assert_eq!(get(&app, "/docs/").0, 404);
+    assert_eq!(get(&app, "/docs/a/b"), (200, String::from("Some(\"a/b\")")));
+}
+
+#[test]
+fn routes_to_closures_that_capture_state() {
+    let mut app = Bobby::new();
+    let hits = Arc::new(AtomicUsize::new(0));
+    let greeting = String::from("Hello");
+
+    let counter = Arc::clone(&hits);
+    app.get("/", move |_| {
+        let hits = counter.fetch_add(1, Ordering::SeqCst) + 1;
+
+        Response::text(format!("{} #{}", greeting, hits))
+    });
+
+    assert_eq!(get(&app, "/"), (200, String::from("Hello #1")));
+    assert_eq!(get(&app, "/"), (200, String::from("Hello #2")));
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn registers_closures_for_a_resource() {
+    let mut app = Bobby::new();
+    let name = String::from("users");
+
+    app.resource(
+        "/users/{id}",
+        Resource::new()
+            .get(move |req| Response::text(format!("{} {:?}", name, req.param("id"))))
+            .delete(|_| Response::text("Deleted")),
+    );
+
+    assert_eq!(
+        get(&app, "/users/5"),
+        (200, String::from("users Some(\"5\")"))
+    );
+
+    let response = app.handle(Method::PUT, "/users/5", HeaderMap::new(), "");
+    assert_eq!(response.status(), 405);
+    assert_eq!(response.header("allow"), Some("GET, DELETE, HEAD"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn leaves_redirect_routes_out_of_the_spec() {
+    let mut app = Bobby::new();
+
+    app.get("/articles/{id}", |_| Response::html("An article."));
+    app.redirect_route("/posts/{id}", "/articles/{id}", 308);
+
+    let spec = app.openapi_spec("Test", "1.0.0");
+
+    assert!(spec["paths"].get("/articles/{id}").is_some());
+    assert!(spec["paths"].get("/posts/{id}").is_none());
+}
+
+fn redirect(app: &Bobby, uri: &str) -> (u16, Option<String>) {
+    let response = app.handle(Method::GET, uri, HeaderMap::new(), "");
+
+    (
+        response.status(),
+        response.header("Location").map(String::from),
+    )
+}
+
+#[test]
+fn fills_params_and_the_query_into_redirect_targets() {
+    let mut app = Bobby::new();
+
+    app.redirect_route("/posts/{id}", "/articles/{id}", 308);
+    app.redirect_route("/docs/{path:*}", "https://docs.example.com/{path}", 301);
+
+    assert_eq!(
+        redirect(&app, "/posts/5?ref=rss"),
+        (308, Some(String::from("/articles/5?ref=rss")))
+    );
+    assert_eq!(
+        redirect(&app, "/docs/guide/intro"),
+        (
+            301,
+            Some(String::from("https://docs.example.com/guide/intro"))
+        )
+    );
+}
+
+#[test]
+fn refuses_redirects_without_a_redirect_status() {
+    let mut app = Bobby::new();
+
+    for status in [200, 299, 400] {
+        assert!(matches!(
+            app.try_redirect_route("/a", "/b", status),
+            Err(RouteError::InvalidRedirectStatus(s)) if s == status
+        ));
+    }
+
+    assert!(app.routes().is_empty());
+    assert!(app.try_redirect_route("/a", "/b", 399).is_ok());
+    assert_eq!(app.routes().len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "isn't a 3xx one")]
+fn panics_on_a_redirect_without_a_redirect_status() {
+    Bobby::new().redirect_route("/a", "/b", 200);
+}
+
+#[test]
+fn prefixes_redirect_targets_within_groups() {
+    let mut app = Bobby::new();
+
+    app.group("/api", |api| {
+        api.get("/", |_| Response::text("Root"));
+
+        api.group("/v1", |v1| {
+            v1.get("/new/{id}", |req| Response::text(req.param("id").unwrap()));
+            v1.redirect_route("/old/{id}", "/new/{id}", 301);
+            v1.redirect_route("/docs", "https://docs.example.com/", 302);
+        });
+    });
+
+    assert_eq!(get(&app, "/api"), (200, String::from("Root")));
+    assert!(app.routes().contains(&(Method::GET, "/api")));
+
+    assert_eq!(
+        redirect(&app, "/api/v1/old/5"),
+        (301, Some(String::from("/api/v1/new/5")))
+    );
+    assert_eq!(get(&app, "/api/v1/new/5"), (200, String::from("5")));
+    assert_eq!(
+        redirect(&app, "/api/v1/docs"),
+        (302, Some(String::from("https://docs.example.com/")))
+    );
+}
+
+fn by_id(_: bobby::Request) -> Response {
+    Response::text("id")
+}
+
+fn by_name(_: bobby::Request) -> Response {
+    Response::text("name")
+}
+
+#[test]
+fn registers_conflicting_routes_by_default_with_the_first_winning() {
+    let mut app = Bobby::new();
+
+    assert!(app.try_route(Method::GET, "/users/{id}", by_id).is_ok());
+    assert!(app.try_route(Method::GET, "/users/{name}", by_name).is_ok());
+    assert!(
+        app.try_route(Method::POST, "/users/{name}", by_name)
+            .is_ok()
+    );
+
+    assert_eq!(app.routes().len(), 3);
+    assert_eq!(get(&app, "/users/5"), (200, String::from("id")));
+}
+
+#[test]
+fn rejects_conflicting_routes_when_asked_to() {
+    let mut app = Bobby::new();
+
+    app.with_route_conflicts(RouteConflict::Reject);
+    app.get("/users/{id}", by_id);
+
+    assert!(matches!(
+        app.try_route(Method::GET, "/users/{name}", by_name),
+        Err(RouteError::Conflict(existing)) if existing == "/users/{id}"
+    ));
+
+    // other methods and more specific patterns don't conflict
+    assert!(
+        app.try_route(Method::POST, "/users/{name}", by_name)
+            .is_ok()
+    );
+    assert!(app.try_route(Method::GET, "/users/me", by_name).is_ok());
+    assert_eq!(get(&app, "/users/5"), (200, String::from("id")));
+}
+
+#[test]
+fn allows_conflicting_routes_when_asked_to() {
+    let mut app = Bobby::new();
+
+    app.with_route_conflicts(RouteConflict::Allow);
+    app.get("/users/{id}", by_id);
+
+    assert!(app.try_route(Method::GET, "/users/{name}", by_name).is_ok());
+    assert_eq!(get(&app, "/users/5"), (200, String::from("id")));
+}
+
+#[test]
+fn tells_trailing_slashes_apart_only_when_strict() {
+    let mut app = Bobby::new();
+
+    app.with_route_conflicts(RouteConflict::Reject);
+    app.get("/t/", by_id);
+
+    assert!(matches!(
+        app.try_route(Method::GET, "/t", by_name),
+        Err(RouteError::Conflict(existing)) if existing == "/t/"
+    ));
+
+    let mut app = Bobby::new();
+
+    app.with_route_conflicts(RouteConflict::Reject)
+        .with_trailing_slash(TrailingSlash::Strict);
+    app.get("/t/", by_id);
+
+    assert!(app.try_route(Method::GET, "/t", by_name).is_ok());
+    assert_eq!(get(&app, "/t/"), (200, String::from("id")));
+    assert_eq!(get(&app, "/t"), (200, String::from("name")));
+}
+
+#[test]
+#[should_panic(expected = "Conflicts with \"/users/{id}\"")]
+fn panics_on_a_rejected_conflict_outside_try_route() {
+    let mut app = Bobby::new();
+
+    app.with_route_conflicts(RouteConflict::Reject);
+    app.get("/users/{id}", by_id);
+    app.get("/users/{name}", by_name);
+}
+
+fn route_error(path: &str) -> String {
+    match Bobby::new().try_route(Method::GET, path, by_id) {
+        Ok(_) => String::from("registered"),
+        Err(err) => format!("{:?}", err),
+    }
+}
+
+#[test]
+fn refuses_malformed_patterns() {
+    let mut app = Bobby::new();
+
+    assert!(matches!(
+        app.try_route(Method::GET, "/{a}/{a}", by_id),
+        Err(RouteError::DuplicateParam(name)) if name == "a"
+    ));
+    assert!(matches!(
+        app.try_route(Method::GET, "/users/{id", by_id),
+        Err(RouteError::UnbalancedBraces(segment)) if segment == "{id"
+    ));
+    assert!(matches!(
+        app.try_route(Method::GET, "/users/x{id}", by_id),
+        Err(RouteError::UnbalancedBraces(segment)) if segment == "x{id}"
+    ));
+    assert!(matches!(
+        app.try_route(Method::GET, "/files/{*path}/raw", by_id),
+        Err(RouteError::WildcardNotLast(segment)) if segment == "{*path}"
+    ));
+    assert!(matches!(
+        app.try_route(Method::GET, "/files/{path:+}/raw", by_id),
+        Err(RouteError::WildcardNotLast(segment)) if segment == "{+path}"
+    ));
+
+    for path in ["/users/{}", "/users/{user id}", "/users/{id!}"] {
+        assert!(
+            matches!(
+                app.try_route(Method::GET, path, by_id),
+                Err(RouteError::InvalidParamName(_))
+            ),
+            "{}",
+            path
+        );
+    }
+
+    assert!(matches!(
+        app.try_route(Method::GET, "/users/{id:[0-9}", by_id),
+        Err(RouteError::InvalidConstraint(segment)) if segment == "{id:[0-9}"
+    ));
+
+    assert!(app.routes().is_empty());
+    assert_eq!(route_error("/{a}/{a}"), "Duplicate param \"a\"");
+    assert_eq!(route_error("/users/{id}/{name?}/{*rest}"), "registered");
+}
+
+#[test]
+fn stops_registering_routes_past_the_limit() {
+    let mut app = Bobby::new();
+
+    app.with_max_routes(2);
+
+    assert!(app.try_route(Method::GET, "/a", by_id).is_ok());
+    assert!(app.try_route(Method::GET, "/b", by_id).is_ok());
+    assert!(matches!(
+        app.try_route(Method::GET, "/c", by_id),
+        Err(RouteError::TooManyRoutes(2))
+    ));
+    assert_eq!(app.routes().len(), 2);
+}
+
+#[test]
+fn prefers_the_most_specific_route_over_the_first_registered() {
+    let mut app = Bobby::new();
+
+    app.get("/{section}/{*rest}", |_| Response::text("wildcard"));
+    app.get("/users/{id?}", |_| Response::text("optional"));
+    app.get("/users/{id}", |_| Response::text("param"));
+    app.get("/users/{id:\\d+}", |_| Response::text("constrained"));
+    app.get("/users/me", |_| Response::text("literal"));
+    app.get("/{section}/posts", |_| Response::text("posts"));
+
+    assert_eq!(get(&app, "/users/me"), (200, String::from("literal")));
+    assert_eq!(get(&app, "/users/5"), (200, String::from("constrained")));
+    assert_eq!(get(&app, "/users/bob"), (200, String::from("param")));
+    assert_eq!(get(&app, "/users"), (200, String::from("optional")));
+    assert_eq!(get(&app, "/users/posts"), (200, String::from("param")));
+    assert_eq!(get(&app, "/blog/posts"), (200, String::from("posts")));
+    assert_eq!(get(&app, "/blog/a/b"), (200, String::from("wildcard")));
+}
+
+#[test]
+fn only_matches_params_meeting_their_constraint() {
+    let mut app = Bobby::new();
+
+    app.get("/user/{id:\\d+}", |req| {
+        Response::text(format!("id {}", req.param("id").unwrap()))
+    });
+    app.get("/user/{name:[a-z]+}", |req| {
+        Response::text(format!("name {}", req.param("name").unwrap()))
+    });
+    app.get("/report/{fmt:json|xml}", |req| {
+        Response::text(req.param("fmt").unwrap())
+    });
+
+    assert_eq!(get(&app, "/user/42"), (200, String::from("id 42")));
+    assert_eq!(get(&app, "/user/abc"), (200, String::from("name abc")));
+    assert_eq!(get(&app, "/user/Abc1").0, 404);
+
+    assert_eq!(get(&app, "/report/json"), (200, String::from("json")));
+    assert_eq!(get(&app, "/report/xml"), (200, String::from("xml")));
+    assert_eq!(get(&app, "/report/jsonx").0, 404);
+    assert_eq!(get(&app, "/report/csv").0, 404);
+}
+
+fn trailing_slash_app(mode: TrailingSlash) -> Bobby {
+    let mut app = Bobby::new();
+
+    app.with_trailing_slash(mode);
+    app.get("/users", |_| Response::text("users"));
+    app.post("/users", |_| Response::text("created"));
+    app.get("/docs/", |_| Response::text("docs"));
+
+    app
+}
+
+#[test]
+fn matches_either_form_of_a_path_when_relaxed() {
+    let app = trailing_slash_app(TrailingSlash::Relaxed);
+
+    assert_eq!(get(&app, "/users/"), (200, String::from("users")));
+    assert_eq!(get(&app, "/docs"), (200, String::from("docs")));
+}
+
+#[test]
+fn matches_only_the_registered_form_when_strict() {
+    let app = trailing_slash_app(TrailingSlash::Strict);
+
+    assert_eq!(get(&app, "/users"), (200, String::from("users")));
+    assert_eq!(get(&app, "/users/").0, 404);
+    assert_eq!(get(&app, "/docs/"), (200, String::from("docs")));
+    assert_eq!(get(&app, "/docs").0, 404);
+}
+
+#[test]
+fn redirects_to_the_registered_form() {
+    let app = trailing_slash_app(TrailingSlash::Redirect);
+
+    assert_eq!(
+        redirect(&app, "/users/?page=2"),
+        (301, Some(String::from("/users?page=2")))
+    );
+    assert_eq!(redirect(&app, "/docs"), (301, Some(String::from("/docs/"))));
+    assert_eq!(get(&app, "/users"), (200, String::from("users")));
+
+    let response = app.handle(Method::POST, "/users/", HeaderMap::new(), "");
+
+    assert_eq!(response.status(), 308);
+    assert_eq!(response.header("Location"), Some("/users"));
+
+    // collapsed, so the location can't turn into another host
+    assert_eq!(
+        redirect(&app, "//docs"),
+        (301, Some(String::from("/docs/")))
+    );
+}
+
+fn get_from(app: &Bobby, host: &str, uri: &str) -> (u16, String) {
+    let mut headers = HeaderMap::new();
+
+    headers.insert("host", host.parse().unwrap());
+
+    let response = app.handle(Method::GET, uri, headers, "");
+    let body = response.body().as_bytes().unwrap_or_default();
+
+    (
+        response.status(),
+        String::from_utf8_lossy(body).into_owned(),
+    )
+}
+
+#[test]
+fn captures_params_from_the_host() {
+    let mut app = Bobby::new();
+
+    app.get("/projects/{id}", |req| {
+        Response::text(format!(
+            "{} {}",
+            req.param("tenant").unwrap(),
+            req.param("id").unwrap()
+        ))
+    })
+    .host("{tenant}.example.com");
+    app.get("/projects/{id}", |_| Response::text("no tenant"));
+
+    assert_eq!(
+        get_from(&app, "acme.example.com", "/projects/5"),
+        (200, String::from("acme 5"))
+    );
+    assert_eq!(
+        get_from(&app, "Acme.Example.com:8080", "/projects/5"),
+        (200, String::from("acme 5"))
+    );
+    assert_eq!(
+        get_from(&app, "a.b.example.com", "/projects/5"),
+        (200, String::from("no tenant"))
+    );
+    assert_eq!(
+        get_from(&app, "example.com", "/projects/5"),
+        (200, String::from("no tenant"))
+    );
+}